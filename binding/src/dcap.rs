@@ -0,0 +1,253 @@
+use std::{convert::TryInto, mem};
+
+use chrono::{DateTime, TimeZone, Utc};
+use dcap::{Collateral, CollateralBundle, EnclavePolicy, QuoteRef, SgxExtensions};
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+use x509_cert::{der::DecodePem, Certificate};
+
+use crate::utils;
+
+/// Verifies SGX and TDX quotes against a collateral bundle provided by the caller.
+#[wasm_bindgen]
+pub struct Dcap {
+    collateral: Collateral,
+    policy: EnclavePolicy,
+}
+
+#[wasm_bindgen]
+impl Dcap {
+    /// `collateral` is a JSON collateral bundle anchored at the Intel SGX Root CA.
+    #[wasm_bindgen(constructor)]
+    pub fn new(collateral: &str) -> Result<Dcap, String> {
+        utils::set_panic_hook();
+        let collateral = CollateralBundle::load(collateral)
+            .and_then(|bundle| bundle.collateral())
+            .map_err(|err| err.to_string())?;
+        Ok(Self {
+            collateral,
+            policy: EnclavePolicy::default(),
+        })
+    }
+
+    /// Same as the constructor, anchored at the PEM encoded `root_ca` instead of Intel's.
+    #[wasm_bindgen(js_name = withRootCa)]
+    pub fn with_root_ca(collateral: &str, root_ca: &str) -> Result<Dcap, String> {
+        utils::set_panic_hook();
+        let root_ca = Certificate::from_pem(root_ca).map_err(|err| err.to_string())?;
+        let collateral = CollateralBundle::load(collateral)
+            .and_then(|bundle| bundle.collateral_with_root_ca(root_ca))
+            .map_err(|err| err.to_string())?;
+        Ok(Self {
+            collateral,
+            policy: EnclavePolicy::default(),
+        })
+    }
+
+    /// Accept debug enclaves and TDs.
+    #[wasm_bindgen(js_name = allowDebug)]
+    pub fn allow_debug(&mut self) {
+        self.policy = mem::take(&mut self.policy).allow_debug();
+    }
+
+    /// Skip the revocation check of certificates whose issuer's CRL is not in the bundle.
+    #[wasm_bindgen(js_name = allowMissingCrls)]
+    pub fn allow_missing_crls(&mut self) {
+        self.collateral.allow_missing_crls();
+    }
+
+    /// Only accept the given MRENCLAVEs, any enclave is accepted if none is trusted.
+    #[wasm_bindgen(js_name = trustEnclave)]
+    pub fn trust_enclave(&mut self, mr_enclave: &[u8]) -> Result<(), String> {
+        let mr_enclave = mr_enclave
+            .try_into()
+            .map_err(|_| "MRENCLAVE must be 32 bytes".to_string())?;
+        self.policy = mem::take(&mut self.policy).trust_enclave(mr_enclave);
+        Ok(())
+    }
+
+    /// Only accept the given MRSIGNERs, any signer is accepted if none is trusted.
+    #[wasm_bindgen(js_name = trustSigner)]
+    pub fn trust_signer(&mut self, mr_signer: &[u8]) -> Result<(), String> {
+        let mr_signer = mr_signer
+            .try_into()
+            .map_err(|_| "MRSIGNER must be 32 bytes".to_string())?;
+        self.policy = mem::take(&mut self.policy).trust_signer(mr_signer);
+        Ok(())
+    }
+
+    /// The header, enclave or TD report, QE report and PCK certificate chain of a quote.
+    #[wasm_bindgen(js_name = parseQuote)]
+    pub fn parse_quote(quote: &[u8]) -> Result<JsValue, String> {
+        to_js(&inspect_quote(quote)?)
+    }
+
+    /// Verify a quote as of `now`, in milliseconds since the epoch as returned by `Date.now()`,
+    /// defaulting to the current time.
+    ///
+    /// Returns the verification report with the overall `tcb_status`, or the step verification
+    /// failed at and why.
+    #[wasm_bindgen]
+    pub fn verify(&self, quote: &[u8], now: Option<f64>) -> Result<JsValue, String> {
+        let now = from_millis(now.unwrap_or_else(js_sys::Date::now))?;
+        to_js(&self.verify_at(quote, now)?)
+    }
+}
+
+impl Dcap {
+    /// The verification report of `quote` as of `now`, see [`Dcap::verify`].
+    fn verify_at(&self, quote: &[u8], now: DateTime<Utc>) -> Result<serde_json::Value, String> {
+        let quote = QuoteRef::parse(quote).map_err(|err| err.to_string())?;
+        let report = quote
+            .verify_steps_at(&self.collateral, &self.policy, now)
+            .map_err(|err| err.to_string())?;
+
+        let mut value = serde_json::to_value(&report).map_err(|err| err.to_string())?;
+        value["tcb_status"] = json!(report.tcb_status().to_string());
+        value["advisory_ids"] = json!(report.advisory_ids());
+        Ok(value)
+    }
+}
+
+/// See [`Dcap::parse_quote`].
+fn inspect_quote(quote: &[u8]) -> Result<serde_json::Value, String> {
+    let quote = QuoteRef::parse(quote).map_err(|err| err.to_string())?;
+    let body = quote.to_body();
+    let auth_data = quote.auth_data();
+    let certificates = quote
+        .certs()
+        .map_err(|err| err.to_string())?
+        .iter()
+        .map(inspect_cert)
+        .collect::<Vec<_>>();
+    Ok(json!({
+        "header": body.header,
+        "report": body.report,
+        "qe_report": auth_data.qe_report().map_err(|err| err.to_string())?,
+        "attestation_key": hex::encode(auth_data.ecdsa_attestation_key),
+        "cert_chain": String::from_utf8_lossy(quote.cert_chain_pem()),
+        "certificates": certificates,
+    }))
+}
+
+fn inspect_cert(cert: &Certificate) -> serde_json::Value {
+    let tbs = &cert.tbs_certificate;
+    let mut value = json!({
+        "subject": tbs.subject.to_string(),
+        "issuer": tbs.issuer.to_string(),
+        "not_before": tbs.validity.not_before.to_string(),
+        "not_after": tbs.validity.not_after.to_string(),
+    });
+    // Only the PCK certificate carries SGX extensions.
+    if let Ok(extensions) = SgxExtensions::from_cert(cert) {
+        value["sgx_extensions"] = json!(extensions);
+    }
+    value
+}
+
+fn from_millis(now: f64) -> Result<DateTime<Utc>, String> {
+    // `as` would turn NaN into the epoch.
+    Some(now)
+        .filter(|now| now.is_finite())
+        .and_then(|now| Utc.timestamp_millis_opt(now as i64).single())
+        .ok_or_else(|| format!("Invalid verification time {}", now))
+}
+
+fn to_js(value: &serde_json::Value) -> Result<JsValue, String> {
+    js_sys::JSON::parse(&value.to_string()).map_err(|_| "Failed to convert to a JS value".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use dcap::{
+        testing::{self, QuoteBuilder, TestCa},
+        BinRepr,
+    };
+
+    use super::*;
+
+    fn dcap() -> Dcap {
+        let root_ca = testing::chain_pem(&[&TestCa::root("Test SGX Root CA").cert]);
+        Dcap::with_root_ca(&testing::bundle().to_json().unwrap(), &root_ca).unwrap()
+    }
+
+    #[test]
+    fn test_inspect_quote() {
+        let quote = QuoteBuilder::sgx().build().to_bytes().unwrap();
+        let value = inspect_quote(&quote).unwrap();
+        assert_eq!(value["header"]["version"], 3);
+        assert_eq!(value["certificates"].as_array().unwrap().len(), 3);
+        assert!(value["certificates"][0]["sgx_extensions"].is_object());
+        assert!(value["cert_chain"]
+            .as_str()
+            .unwrap()
+            .starts_with("-----BEGIN CERTIFICATE-----"));
+
+        assert!(inspect_quote(&quote[..quote.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let quote = QuoteBuilder::sgx().build().to_bytes().unwrap();
+        let now = testing::verification_time();
+        let mut dcap = dcap();
+        let report = dcap.verify_at(&quote, now).unwrap();
+        assert_eq!(report["tcb_status"], "SWHardeningNeeded");
+        assert!(report["advisory_ids"].is_array());
+
+        dcap.trust_signer(&[0x44; 32]).unwrap();
+        let err = dcap.verify_at(&quote, now).unwrap_err();
+        assert!(err.starts_with("STEP2"), "{}", err);
+        assert!(dcap.trust_signer(&[0x44; 31]).is_err());
+
+        // The test collateral is not anchored at Intel.
+        assert!(Dcap::new(&testing::bundle().to_json().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_from_millis() {
+        let now = testing::verification_time();
+        assert_eq!(from_millis(now.timestamp_millis() as f64), Ok(now));
+        assert!(from_millis(f64::MAX).is_err());
+        assert!(from_millis(f64::NAN).is_err());
+        assert!(from_millis(f64::INFINITY).is_err());
+    }
+
+    /// The conversions to JS values, run in the browser. Parsing and verification are covered
+    /// natively above.
+    #[cfg(target_arch = "wasm32")]
+    mod wasm {
+        use wasm_bindgen_test::*;
+
+        use super::*;
+
+        wasm_bindgen_test_configure!(run_in_browser);
+
+        fn get(value: &JsValue, key: &str) -> JsValue {
+            js_sys::Reflect::get(value, &key.into()).unwrap()
+        }
+
+        #[wasm_bindgen_test]
+        fn parse_quote() {
+            let quote = QuoteBuilder::sgx().build().to_bytes().unwrap();
+            let value = Dcap::parse_quote(&quote).unwrap();
+            assert_eq!(get(&get(&value, "header"), "version"), 3);
+            assert!(get(&value, "cert_chain")
+                .as_string()
+                .unwrap()
+                .starts_with("-----BEGIN CERTIFICATE-----"));
+        }
+
+        #[wasm_bindgen_test]
+        fn verify() {
+            let quote = QuoteBuilder::sgx().build().to_bytes().unwrap();
+            let now = Some(testing::verification_time().timestamp_millis() as f64);
+            let mut dcap = dcap();
+            let report = dcap.verify(&quote, now).unwrap();
+            assert_eq!(get(&report, "tcb_status"), "SWHardeningNeeded");
+
+            dcap.trust_signer(&[0x44; 32]).unwrap();
+            assert!(dcap.verify(&quote, now).is_err());
+        }
+    }
+}
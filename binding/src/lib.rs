@@ -1,3 +1,5 @@
 pub mod aes;
+#[cfg(feature = "dcap")]
+pub mod dcap;
 pub mod shamir;
 mod utils;
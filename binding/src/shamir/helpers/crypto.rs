@@ -77,19 +77,16 @@ mod tests {
 
     #[test]
     fn test_calculate_fo_fx() {
-        assert_eq!(96, calculate_fo_fx(1, &vec![116, 107, 127]));
-        assert_eq!(165, calculate_fo_fx(2, &vec![115, 172, 237]));
-        assert_eq!(239, calculate_fo_fx(3, &vec![97, 116, 241]));
-        assert_eq!(246, calculate_fo_fx(4, &vec![104, 183, 137]));
-        assert_eq!(113, calculate_fo_fx(5, &vec![1, 159, 156]));
+        assert_eq!(96, calculate_fo_fx(1, &[116, 107, 127]));
+        assert_eq!(165, calculate_fo_fx(2, &[115, 172, 237]));
+        assert_eq!(239, calculate_fo_fx(3, &[97, 116, 241]));
+        assert_eq!(246, calculate_fo_fx(4, &[104, 183, 137]));
+        assert_eq!(113, calculate_fo_fx(5, &[1, 159, 156]));
     }
 
     #[test]
     fn test_lagrange() {
-        assert_eq!(
-            116,
-            lagrange(&vec![1, 2, 3, 4, 5], &vec![108, 49, 41, 55, 47])
-        );
-        assert_eq!(111, lagrange(&vec![1, 2], &vec![0, 177]));
+        assert_eq!(116, lagrange(&[1, 2, 3, 4, 5], &[108, 49, 41, 55, 47]));
+        assert_eq!(111, lagrange(&[1, 2], &[0, 177]));
     }
 }
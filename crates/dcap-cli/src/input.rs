@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use dcap::{
+    BinRepr, Collateral, ECDSAQuoteV3AuthData, ECDSAQuoteV4AuthData, EnclavePolicy, Quote,
+    QuoteBody, StepResult, VerificationReport,
+};
+
+/// Decode a quote given as raw bytes, hex (with or without `0x`) or base64.
+pub fn decode(raw: &[u8]) -> Vec<u8> {
+    let text = match std::str::from_utf8(raw) {
+        Ok(text) => text,
+        Err(_) => return raw.to_vec(),
+    };
+    let compact: String = text.split_whitespace().collect();
+    if let Ok(bytes) = hex::decode(compact.strip_prefix("0x").unwrap_or(&compact)) {
+        return bytes;
+    }
+    STANDARD.decode(&compact).unwrap_or_else(|_| raw.to_vec())
+}
+
+/// A quote of any supported version.
+pub enum AnyQuote {
+    V3(Quote<ECDSAQuoteV3AuthData>),
+    V4(Quote<ECDSAQuoteV4AuthData>),
+}
+
+impl AnyQuote {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let version = bytes
+            .get(..2)
+            .map(|version| u16::from_le_bytes([version[0], version[1]]))
+            .ok_or_else(|| anyhow!("Quote is empty"))?;
+        let quote = match version {
+            3 => Self::V3(Quote::from_bytes(bytes)?),
+            4 | 5 => Self::V4(Quote::from_bytes(bytes)?),
+            version => return Err(anyhow!("Unsupported quote version {}", version)),
+        };
+        Ok(quote)
+    }
+
+    pub fn body(&self) -> &QuoteBody {
+        match self {
+            Self::V3(quote) => &quote.body,
+            Self::V4(quote) => &quote.body,
+        }
+    }
+
+    pub fn auth_data(&self) -> &ECDSAQuoteV3AuthData {
+        match self {
+            Self::V3(quote) => &quote.signature,
+            Self::V4(quote) => &quote.signature,
+        }
+    }
+
+    pub fn verify_steps_at(
+        &self,
+        collateral: &Collateral,
+        policy: &EnclavePolicy,
+        now: DateTime<Utc>,
+    ) -> StepResult<VerificationReport> {
+        match self {
+            Self::V3(quote) => quote.verify_steps_at(collateral, policy, now),
+            Self::V4(quote) => quote.verify_steps_at(collateral, policy, now),
+        }
+    }
+}
+
+/// Read and parse a quote from `path`, `-` for stdin.
+pub fn read_quote(path: &str) -> Result<AnyQuote> {
+    let raw = if path == "-" {
+        let mut raw = Vec::new();
+        std::io::Read::read_to_end(&mut std::io::stdin(), &mut raw)?;
+        raw
+    } else {
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path))?
+    };
+    AnyQuote::parse(&decode(&raw)).context("Failed to parse quote")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let bytes = [3u8, 0, 2, 0, 0xff, 0x10];
+        assert_eq!(decode(&bytes), bytes);
+        assert_eq!(decode(b"030002\n00ff10\n".as_slice()), bytes);
+        assert_eq!(decode(b"0x03000200ff10"), bytes);
+        assert_eq!(decode(STANDARD.encode(bytes).as_bytes()), bytes);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_version() {
+        assert!(AnyQuote::parse(&[]).is_err());
+        assert!(AnyQuote::parse(&[6, 0, 2, 0]).is_err());
+        assert!(AnyQuote::parse(&[3, 0, 2, 0]).is_err());
+    }
+}
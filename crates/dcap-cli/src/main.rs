@@ -0,0 +1,193 @@
+//! Inspect and verify SGX and TDX DCAP quotes.
+//!
+//! ```bash
+//! dcap inspect quote.bin
+//! dcap verify quote.hex --collateral collateral/
+//! dcap calldata quote.bin
+//! ```
+
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use dcap::{intel_root_ca, CollateralBundle, EnclavePolicy, QuoteCalldata, SgxExtensions};
+use serde_json::json;
+use x509_cert::{der::DecodePem, Certificate};
+
+mod input;
+
+#[derive(Parser)]
+#[command(name = "dcap", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the header, enclave or TD report, QE report and PCK certificate chain of a quote.
+    Inspect {
+        /// Quote file, binary, hex or base64 encoded. `-` reads stdin.
+        quote: String,
+    },
+    /// Verify a quote and print its TCB status, or the step verification failed at.
+    Verify {
+        /// Quote file, binary, hex or base64 encoded. `-` reads stdin.
+        quote: String,
+        /// Collateral bundle file or directory of PCS documents, see `CollateralBundle::load_path`.
+        #[arg(long)]
+        collateral: PathBuf,
+        /// PEM encoded root CA to trust instead of the Intel SGX Root CA.
+        #[arg(long)]
+        root_ca: Option<PathBuf>,
+        /// Verification time as RFC 3339, defaults to now.
+        #[arg(long)]
+        time: Option<DateTime<Utc>>,
+        /// Accept debug enclaves and TDs.
+        #[arg(long)]
+        allow_debug: bool,
+        /// Skip the revocation check of certificates whose issuer's CRL is not in the collateral.
+        #[arg(long)]
+        allow_missing_crls: bool,
+        /// Accepted MRENCLAVE, hex encoded. Any enclave is accepted if none is given.
+        #[arg(long)]
+        mr_enclave: Vec<String>,
+        /// Accepted MRSIGNER, hex encoded. Any signer is accepted if none is given.
+        #[arg(long)]
+        mr_signer: Vec<String>,
+    },
+    /// Print the ABI encoded calldata of an SGX quote for on-chain verifiers, hex encoded.
+    Calldata {
+        /// Quote file, binary, hex or base64 encoded. `-` reads stdin.
+        quote: String,
+        /// Function selector to prepend, hex encoded.
+        #[arg(long)]
+        selector: Option<String>,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {:#}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<ExitCode> {
+    match cli.command {
+        Command::Inspect { quote } => {
+            let quote = input::read_quote(&quote)?;
+            println!("{}", serde_json::to_string_pretty(&inspect(&quote)?)?);
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Verify {
+            quote,
+            collateral,
+            root_ca,
+            time,
+            allow_debug,
+            allow_missing_crls,
+            mr_enclave,
+            mr_signer,
+        } => {
+            let quote = input::read_quote(&quote)?;
+            let root_ca = match root_ca {
+                Some(path) => Certificate::from_pem(std::fs::read(&path)?)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?,
+                None => intel_root_ca(),
+            };
+            let mut collateral = CollateralBundle::load_path(&collateral, &root_ca)
+                .and_then(|bundle| bundle.collateral_with_root_ca(root_ca))
+                .context("Failed to load collateral")?;
+            if allow_missing_crls {
+                collateral.allow_missing_crls();
+            }
+
+            let mut policy = EnclavePolicy::default();
+            if allow_debug {
+                policy = policy.allow_debug();
+            }
+            for mr_enclave in mr_enclave {
+                policy = policy.trust_enclave(parse_hex(&mr_enclave)?);
+            }
+            for mr_signer in mr_signer {
+                policy = policy.trust_signer(parse_hex(&mr_signer)?);
+            }
+
+            let now = time.unwrap_or_else(Utc::now);
+            match quote.verify_steps_at(&collateral, &policy, now) {
+                Ok(report) => {
+                    println!("Verified at {}", now.to_rfc3339());
+                    println!("TCB status: {}", report.tcb_status());
+                    println!("FMSPC: {}", hex::encode(report.fmspc));
+                    println!("QE identity status: {}", report.qe_tcb_level.tcb_status);
+                    if let Some(level) = &report.tdx_module_tcb_level {
+                        println!("TDX module TCB status: {}", level.tcb_status);
+                    }
+                    if !report.advisory_ids().is_empty() {
+                        println!("Advisory IDs: {}", report.advisory_ids().join(", "));
+                    }
+                    Ok(ExitCode::SUCCESS)
+                }
+                Err(err) => {
+                    println!("Verification failed at {}", err.step);
+                    println!("{}", err.error);
+                    Ok(ExitCode::FAILURE)
+                }
+            }
+        }
+        Command::Calldata { quote, selector } => {
+            let quote = input::read_quote(&quote)?;
+            let calldata = QuoteCalldata::new(quote.body(), quote.auth_data())?;
+            let calldata = match selector {
+                Some(selector) => calldata.encode_call(parse_hex(&selector)?),
+                None => calldata.encode(),
+            };
+            println!("0x{}", hex::encode(calldata));
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn inspect(quote: &input::AnyQuote) -> Result<serde_json::Value> {
+    let body = quote.body();
+    let auth_data = quote.auth_data();
+    // Keep printing what can be parsed of a broken chain, it is usually what is being debugged.
+    let certificates = match auth_data.qe_cert.certs() {
+        Ok(certs) => certs.iter().map(inspect_cert).collect(),
+        Err(err) => vec![json!({ "error": err.to_string() })],
+    };
+    Ok(json!({
+        "header": body.header,
+        "report": body.report,
+        "qe_report": auth_data.qe_report()?,
+        "attestation_key": hex::encode(auth_data.ecdsa_attestation_key),
+        "certificates": certificates,
+    }))
+}
+
+fn inspect_cert(cert: &Certificate) -> serde_json::Value {
+    let tbs = &cert.tbs_certificate;
+    let mut value = json!({
+        "subject": tbs.subject.to_string(),
+        "issuer": tbs.issuer.to_string(),
+        "serial_number": hex::encode(tbs.serial_number.as_bytes()),
+        "not_before": tbs.validity.not_before.to_string(),
+        "not_after": tbs.validity.not_after.to_string(),
+    });
+    // Only the PCK certificate carries SGX extensions.
+    if let Ok(extensions) = SgxExtensions::from_cert(cert) {
+        value["sgx_extensions"] = json!(extensions);
+    }
+    value
+}
+
+fn parse_hex<const N: usize>(raw: &str) -> Result<[u8; N]> {
+    hex::decode(raw.strip_prefix("0x").unwrap_or(raw))?
+        .try_into()
+        .map_err(|_| anyhow!("Expected {} hex encoded bytes: {}", N, raw))
+}
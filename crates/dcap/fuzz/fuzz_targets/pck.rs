@@ -0,0 +1,12 @@
+#![no_main]
+
+use dcap::PCK;
+use der::Decode;
+use libfuzzer_sys::fuzz_target;
+use x509_cert::Certificate;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(cert) = Certificate::from_der(data) {
+        let _ = PCK::new(cert);
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use dcap::{BinRepr, QECertData, PCK};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(cert_data) = QECertData::from_bytes(data) {
+        assert_eq!(cert_data.to_bytes().unwrap(), data);
+        if let Ok([pck, _, _]) = cert_data.certs() {
+            let _ = PCK::new(pck);
+        }
+    }
+});
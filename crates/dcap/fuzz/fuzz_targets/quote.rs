@@ -0,0 +1,11 @@
+#![no_main]
+
+use dcap::{BinRepr, ECDSAQuoteV3AuthData, Quote};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(quote) = Quote::<ECDSAQuoteV3AuthData>::from_bytes(data) {
+        assert_eq!(quote.to_bytes().unwrap(), data);
+        let _ = quote.signature.qe_cert.certs();
+    }
+});
@@ -0,0 +1,449 @@
+//! Ethereum ABI encoding of the inputs an on-chain verifier needs to check a quote.
+//!
+//! The calldata is the ABI encoding of
+//!
+//! ```solidity
+//! (bytes quoteBody, bytes qeReport, bytes qeAuthData, bytes pckCaTbs, bytes pckTbs,
+//!  bytes32[2][4] signatures, bytes32[2] attestationKey, uint256[5] offsets)
+//! ```
+//!
+//! It carries the exact bytes every signature covers, so the contract hashes each of them
+//! itself and only trusts fields it read from a preimage whose signature it checked:
+//!
+//! | Field            | Content                                                            |
+//! |------------------|--------------------------------------------------------------------|
+//! | `quoteBody`      | Quote header and enclave report, signed by the attestation key     |
+//! | `qeReport`       | QE report, signed by the PCK key                                   |
+//! | `qeAuthData`     | QE authentication data                                             |
+//! | `pckCaTbs`       | DER `TBSCertificate` of the PCK Platform or Processor CA           |
+//! | `pckTbs`         | DER `TBSCertificate` of the PCK certificate                        |
+//! | `signatures`     | `(r, s)` over `pckCaTbs`, `pckTbs`, `qeReport` and `quoteBody`     |
+//! | `attestationKey` | `(x, y)` of the attestation key                                    |
+//! | `offsets`        | Offsets of the PCK CA and PCK keys and of the FMSPC, PCE ID and    |
+//! |                  | TCB SGX extensions in `pckCaTbs` and `pckTbs`, see [`TbsOffsets`]  |
+//!
+//! The contract pins the root CA key and checks, in order:
+//!
+//! 1. `signatures[0]` over `sha256(pckCaTbs)` with the root CA key.
+//! 2. `signatures[1]` over `sha256(pckTbs)` with the PCK CA key read from `pckCaTbs`.
+//! 3. `signatures[2]` over `sha256(qeReport)` with the PCK key read from `pckTbs`.
+//! 4. That bytes 320..352 of `qeReport` are `sha256(attestationKey || qeAuthData)`.
+//! 5. `signatures[3]` over `sha256(quoteBody)` with `attestationKey`.
+//!
+//! Each check is a RIP-7212 P256VERIFY call on `hash || r || s || x || y`. The enclave fields are
+//! then read from `quoteBody`, the QE identity fields from `qeReport` and the platform TCB from
+//! the SGX extensions of `pckTbs`. Validity and revocation of the certificates and the TCB
+//! status of the platform are left to the contract.
+
+use super::{
+    auth_data::ECDSAQuoteV3AuthData,
+    cert::sgx_extension_der,
+    error::{DcapError, Result},
+    quote::QuoteBody,
+    signature::EcdsaParams,
+    traits::BinRepr,
+};
+use alloc::{string::ToString, vec::Vec};
+use der::Encode;
+use p256::ecdsa::Signature;
+
+/// Size of an ABI word.
+const WORD: usize = 32;
+
+/// The signed preimages, signatures and TBS offsets of an SGX quote, laid out as documented in
+/// `abi.rs`.
+pub struct QuoteCalldata {
+    /// The encoded quote header and enclave report.
+    pub quote_body: Vec<u8>,
+    pub qe_report: [u8; 384],
+    pub qe_auth_data: Vec<u8>,
+    /// DER encoded `TBSCertificate` of the PCK Platform or Processor CA.
+    pub pck_ca_tbs: Vec<u8>,
+    /// DER encoded `TBSCertificate` of the PCK certificate.
+    pub pck_tbs: Vec<u8>,
+    /// `r || s` of the signatures over `pck_ca_tbs`, `pck_tbs`, `qe_report` and `quote_body`.
+    pub signatures: [[u8; 64]; 4],
+    /// `x || y` of the attestation key.
+    pub attestation_key: [u8; 64],
+    pub offsets: TbsOffsets,
+}
+
+/// Where the fields a contract needs start in the TBS certificates.
+///
+/// Keys point at the `SubjectPublicKeyInfo`, whose 27 bytes P-256 prefix the contract checks
+/// before reading `x || y` right after it. SGX extensions point at their
+/// `SEQUENCE { OID, value }`, whose OID the contract checks before reading the value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TbsOffsets {
+    /// Key of the PCK CA in `pck_ca_tbs`.
+    pub pck_ca_key: usize,
+    /// Key of the PCK certificate in `pck_tbs`.
+    pub pck_key: usize,
+    /// FMSPC extension (1.2.840.113741.1.13.1.4) in `pck_tbs`.
+    pub fmspc: usize,
+    /// PCE ID extension (1.2.840.113741.1.13.1.3) in `pck_tbs`.
+    pub pce_id: usize,
+    /// TCB extension (1.2.840.113741.1.13.1.2) in `pck_tbs`, holding the 16 TCB component SVNs,
+    /// the PCE SVN and the CPUSVN.
+    pub tcb: usize,
+}
+
+impl QuoteCalldata {
+    /// Number of head words: five `bytes` offsets, the signatures, attestation key and offsets.
+    const HEAD_WORDS: usize = 5 + 8 + 2 + 5;
+
+    /// Collect the calldata of a quote from its body and signature data. Only SGX quotes are
+    /// supported.
+    pub fn new(body: &QuoteBody, auth_data: &ECDSAQuoteV3AuthData) -> Result<Self> {
+        if body.report.enclave_report().is_none() {
+            return Err(DcapError::Unsupported("ABI encoding of TD quotes".into()));
+        }
+        let [pck, ca, _] = auth_data.qe_cert.certs()?;
+        let pck_ca_tbs = ca.tbs_certificate.to_der()?;
+        let pck_tbs = pck.tbs_certificate.to_der()?;
+        let offsets = TbsOffsets {
+            pck_ca_key: find(
+                &pck_ca_tbs,
+                &ca.tbs_certificate.subject_public_key_info.to_der()?,
+            )?,
+            pck_key: find(
+                &pck_tbs,
+                &pck.tbs_certificate.subject_public_key_info.to_der()?,
+            )?,
+            fmspc: find(&pck_tbs, &sgx_extension_der(&pck, "4")?)?,
+            pce_id: find(&pck_tbs, &sgx_extension_der(&pck, "3")?)?,
+            tcb: find(&pck_tbs, &sgx_extension_der(&pck, "2")?)?,
+        };
+        let cert_signature = |signature: &der::asn1::BitString| -> Result<[u8; 64]> {
+            Ok(Signature::from_der(signature.raw_bytes())
+                .map_err(|e| DcapError::Parse(e.to_string()))?
+                .to_bytes()
+                .into())
+        };
+        Ok(Self {
+            quote_body: body.to_bytes()?,
+            qe_report: auth_data.raw_qe_report,
+            qe_auth_data: auth_data.qe_auth_data.clone(),
+            signatures: [
+                cert_signature(&ca.signature)?,
+                cert_signature(&pck.signature)?,
+                auth_data.qe_report_signature,
+                auth_data.ecdsa256_bit_signature,
+            ],
+            pck_ca_tbs,
+            pck_tbs,
+            attestation_key: auth_data.ecdsa_attestation_key,
+            offsets,
+        })
+    }
+
+    /// ABI encode the calldata.
+    pub fn encode(&self) -> Vec<u8> {
+        let dynamic: [&[u8]; 5] = [
+            &self.quote_body,
+            &self.qe_report,
+            &self.qe_auth_data,
+            &self.pck_ca_tbs,
+            &self.pck_tbs,
+        ];
+        let mut head = Vec::with_capacity(Self::HEAD_WORDS * WORD);
+        let mut tail = Vec::new();
+        for bytes in dynamic {
+            head.extend_from_slice(&uint(Self::HEAD_WORDS * WORD + tail.len()));
+            tail.extend_from_slice(&uint(bytes.len()));
+            tail.extend_from_slice(bytes);
+            tail.resize(tail.len().next_multiple_of(WORD), 0);
+        }
+        for signature in &self.signatures {
+            head.extend_from_slice(signature);
+        }
+        head.extend_from_slice(&self.attestation_key);
+        let TbsOffsets {
+            pck_ca_key,
+            pck_key,
+            fmspc,
+            pce_id,
+            tcb,
+        } = self.offsets;
+        for offset in [pck_ca_key, pck_key, fmspc, pce_id, tcb] {
+            head.extend_from_slice(&uint(offset));
+        }
+        head.extend(tail);
+        head
+    }
+
+    /// ABI encode the calldata of a call to the function with `selector`.
+    pub fn encode_call(&self, selector: [u8; 4]) -> Vec<u8> {
+        let mut out = selector.to_vec();
+        out.extend(self.encode());
+        out
+    }
+}
+
+impl EcdsaParams {
+    /// The 160 bytes input of the RIP-7212 P256VERIFY precompile: `hash || r || s || x || y`.
+    pub fn rip7212_input(&self) -> [u8; 160] {
+        let mut input = [0; 160];
+        input[..32].copy_from_slice(&self.msghash);
+        input[32..96].copy_from_slice(&self.signature);
+        // Skip the SEC1 uncompressed point tag.
+        input[96..].copy_from_slice(&self.vk[1..]);
+        input
+    }
+}
+
+/// Offset of `needle` in `haystack`, which DER encodings of distinct elements make unique.
+fn find(haystack: &[u8], needle: &[u8]) -> Result<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .ok_or_else(|| DcapError::Parse("Element not found in TBS certificate".into()))
+}
+
+/// A `uint256` value, padded on the left.
+fn uint(value: usize) -> [u8; WORD] {
+    let mut word = [0; WORD];
+    word[WORD - 8..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use anyhow::Result;
+    use der::Decode;
+    use p256::{
+        ecdsa::{signature::Signer, SigningKey, VerifyingKey},
+        EncodedPoint,
+    };
+    use sha2::{Digest, Sha256};
+    use signature::hazmat::PrehashVerifier;
+    use x509_cert::Certificate;
+
+    use super::*;
+    use crate::{
+        cert::INTEL_SGX_ROOT_CA_DER,
+        quote::ReportBody,
+        testing::{TestCa, TestPlatform},
+    };
+
+    /// `SEQUENCE { SEQUENCE { id-ecPublicKey, prime256v1 }, BIT STRING 04 || x || y }`
+    const P256_SPKI_PREFIX: [u8; 27] =
+        hex_literal::hex!("3059301306072a8648ce3d020106082a8648ce3d03010703420004");
+
+    /// What the P256VERIFY precompile does with its input.
+    fn p256_verify(input: &[u8]) -> bool {
+        let point = EncodedPoint::from_affine_coordinates(
+            input[96..128].into(),
+            input[128..160].into(),
+            false,
+        );
+        let Ok(key) = VerifyingKey::from_encoded_point(&point) else {
+            return false;
+        };
+        let Ok(signature) = Signature::from_slice(&input[32..96]) else {
+            return false;
+        };
+        key.verify_prehash(&input[..32], &signature).is_ok()
+    }
+
+    /// The fields a contract trusts once every check of `abi.rs` passed.
+    #[derive(Debug, PartialEq)]
+    struct Verified {
+        quote_body: Vec<u8>,
+        qe_report: Vec<u8>,
+        fmspc: Vec<u8>,
+    }
+
+    /// Tag, header length and content length of the DER element at the start of `bytes`.
+    fn der_header(bytes: &[u8]) -> Option<(u8, usize, usize)> {
+        let (tag, len) = (*bytes.first()?, *bytes.get(1)? as usize);
+        if len < 0x80 {
+            return Some((tag, 2, len));
+        }
+        let n = len & 0x7f;
+        let len = (bytes.get(2..2 + n)?.iter()).fold(0, |len, b| len << 8 | *b as usize);
+        Some((tag, 2 + n, len))
+    }
+
+    /// What a contract pinning `root_key` does with the calldata, see `abi.rs`.
+    fn contract(calldata: &[u8], root_key: &[u8]) -> Option<Verified> {
+        let word = |i: usize| calldata.get(i * WORD..(i + 1) * WORD);
+        let uint = |i: usize| -> Option<usize> {
+            let word = word(i)?;
+            word[..WORD - 8]
+                .iter()
+                .all(|b| *b == 0)
+                .then(|| u64::from_be_bytes(word[WORD - 8..].try_into().unwrap()) as usize)
+        };
+        let bytes = |i: usize| -> Option<&[u8]> {
+            let offset = uint(i)?;
+            let len = uint(offset / WORD)?;
+            calldata.get(offset + WORD..offset + WORD + len)
+        };
+        let [quote_body, qe_report, qe_auth_data, pck_ca_tbs, pck_tbs] = [0, 1, 2, 3, 4].map(bytes);
+        let (quote_body, qe_report, qe_auth_data, pck_ca_tbs, pck_tbs) = (
+            quote_body?,
+            qe_report?,
+            qe_auth_data?,
+            pck_ca_tbs?,
+            pck_tbs?,
+        );
+        let signature = |i: usize| Some([word(5 + 2 * i)?, word(6 + 2 * i)?].concat());
+        let attestation_key = [word(13)?, word(14)?].concat();
+        let offset = |i: usize| uint(15 + i);
+
+        // The `x || y` of a P-256 SubjectPublicKeyInfo at `offset`.
+        let key = |tbs: &[u8], offset: usize| -> Option<Vec<u8>> {
+            let spki = tbs.get(offset..offset + 27 + 64)?;
+            (spki[..27] == P256_SPKI_PREFIX).then(|| spki[27..].to_vec())
+        };
+        // The DER encoded value of the SGX extension at `offset`, a
+        // `SEQUENCE { OID 1.2.840.113741.1.13.1.<arc>, value }`.
+        let extension = |offset: usize, arc: u8| -> Option<&[u8]> {
+            let entry = pck_tbs.get(offset..)?;
+            let (tag, header_len, _) = der_header(entry)?;
+            let oid = [
+                0x06, 0x0a, 0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01, arc,
+            ];
+            if tag != 0x30 || entry.get(header_len..header_len + oid.len())? != oid {
+                return None;
+            }
+            let value = &entry[header_len + oid.len()..];
+            let (_, header_len, len) = der_header(value)?;
+            value.get(..header_len + len)
+        };
+        let p256 = |message: &[u8], signature: Vec<u8>, key: &[u8]| {
+            let input = [&Sha256::digest(message)[..], &signature, key].concat();
+            p256_verify(&input)
+        };
+
+        let pck_ca_key = key(pck_ca_tbs, offset(0)?)?;
+        let pck_key = key(pck_tbs, offset(1)?)?;
+        let report_data = Sha256::new()
+            .chain_update(&attestation_key)
+            .chain_update(qe_auth_data)
+            .finalize();
+        let verified = p256(pck_ca_tbs, signature(0)?, root_key)
+            && p256(pck_tbs, signature(1)?, &pck_ca_key)
+            && p256(qe_report, signature(2)?, &pck_key)
+            && qe_report.get(320..352)? == report_data.as_slice()
+            && p256(quote_body, signature(3)?, &attestation_key);
+        let fmspc = extension(offset(2)?, 4)?;
+        extension(offset(3)?, 3)?;
+        extension(offset(4)?, 2)?;
+        verified.then(|| Verified {
+            quote_body: quote_body.to_vec(),
+            qe_report: qe_report.to_vec(),
+            fmspc: fmspc.to_vec(),
+        })
+    }
+
+    fn root_key() -> Vec<u8> {
+        let root = TestCa::root("Test SGX Root CA").cert;
+        root.tbs_certificate
+            .subject_public_key_info
+            .subject_public_key
+            .raw_bytes()[1..]
+            .to_vec()
+    }
+
+    #[test]
+    fn test_quote_calldata() -> Result<()> {
+        let platform = TestPlatform::new();
+        let body = platform.quote_body(3);
+        let auth_data = platform.sign(&body);
+        let calldata = QuoteCalldata::new(&body, &auth_data)?;
+        let encoded = calldata.encode();
+        assert_eq!(encoded.len() % WORD, 0);
+
+        let verified = contract(&encoded, &root_key()).unwrap();
+        assert_eq!(verified.quote_body, body.to_bytes()?);
+        assert_eq!(verified.qe_report, auth_data.raw_qe_report);
+        // `OCTET STRING` of the FMSPC.
+        assert_eq!(verified.fmspc[..2], [0x04, 6]);
+        assert_eq!(verified.fmspc[2..], TestPlatform::FMSPC);
+
+        // The four verifications line up with the P-256 verifications of the quote.
+        let params = auth_data.ecdsa_params(&body)?;
+        for (params, signature) in params.iter().zip(&calldata.signatures) {
+            assert_eq!(&params.signature, signature);
+            assert!(p256_verify(&params.rip7212_input()));
+        }
+        assert_eq!(params[3].vk[1..], calldata.attestation_key);
+
+        // Only the pinned root is trusted.
+        let intel = Certificate::from_der(INTEL_SGX_ROOT_CA_DER)?;
+        let intel_key = intel
+            .tbs_certificate
+            .subject_public_key_info
+            .subject_public_key;
+        assert!(contract(&encoded, &intel_key.raw_bytes()[1..]).is_none());
+
+        let call = calldata.encode_call([0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(call[..4], [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(call[4..], encoded);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_calldata_binds_fields_to_signatures() -> Result<()> {
+        let platform = TestPlatform::new();
+        let body = platform.quote_body(3);
+        let auth_data = platform.sign(&body);
+        let root_key = root_key();
+        let rejected = |calldata: &QuoteCalldata| contract(&calldata.encode(), &root_key).is_none();
+
+        // An enclave field changed after signing.
+        let mut calldata = QuoteCalldata::new(&body, &auth_data)?;
+        calldata.quote_body[48 + 64] ^= 1;
+        assert!(rejected(&calldata));
+
+        // A body signed by a key of the attacker's choosing, which the QE never certified.
+        let mut forged = platform.quote_body(3);
+        if let ReportBody::Sgx(report) = &mut forged.report {
+            report.mr_enclave = [0x66; 32];
+        }
+        let key = SigningKey::from_slice(&[0x42; 32])?;
+        let signature: Signature = key.sign(&forged.to_bytes()?);
+        let mut calldata = QuoteCalldata::new(&body, &auth_data)?;
+        calldata.quote_body = forged.to_bytes()?;
+        calldata.signatures[3] = signature.to_bytes().into();
+        calldata.attestation_key =
+            key.verifying_key().to_encoded_point(false).as_bytes()[1..].try_into()?;
+        assert!(rejected(&calldata));
+
+        // QE authentication data that the QE report does not commit to.
+        let mut calldata = QuoteCalldata::new(&body, &auth_data)?;
+        calldata.qe_auth_data.push(0);
+        assert!(rejected(&calldata));
+
+        // A PCK certificate field changed after signing.
+        let mut calldata = QuoteCalldata::new(&body, &auth_data)?;
+        let fmspc = calldata.offsets.fmspc + 16;
+        calldata.pck_tbs[fmspc] ^= 1;
+        assert!(rejected(&calldata));
+
+        // Offsets pointing at anything but the element they name.
+        let mut calldata = QuoteCalldata::new(&body, &auth_data)?;
+        calldata.offsets.fmspc = calldata.offsets.pce_id;
+        assert!(rejected(&calldata));
+        let mut calldata = QuoteCalldata::new(&body, &auth_data)?;
+        calldata.offsets.pck_key += 1;
+        assert!(rejected(&calldata));
+
+        assert!(!rejected(&QuoteCalldata::new(&body, &auth_data)?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_calldata_rejects_td_quotes() {
+        let platform = TestPlatform::tdx();
+        let body = platform.quote_body(4);
+        let auth_data = platform.sign(&body);
+        assert!(matches!(body.report, ReportBody::Td10(_)));
+        assert!(matches!(
+            QuoteCalldata::new(&body, &auth_data),
+            Err(DcapError::Unsupported(_))
+        ));
+    }
+}
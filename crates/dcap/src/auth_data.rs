@@ -1,7 +1,8 @@
-use std::convert::TryInto;
+use alloc::{format, string::ToString, vec, vec::Vec};
+use core::convert::TryInto;
 
-use anyhow::{anyhow, Result};
-use der::{Decode, Encode};
+use chrono::{DateTime, Utc};
+use der::Encode;
 use p256::ecdsa::Signature;
 use sha2::{digest::Digest, Sha256};
 use x509_cert::Certificate;
@@ -9,34 +10,29 @@ use x509_cert::Certificate;
 use crate::signature::{verify_signature, EcdsaParams, VerifyingKey};
 
 use super::{
-    cert::PCK,
-    enclave::{EnclaveId, EnclaveIdStatus},
-    tcb_info::TcbInfo,
-    traits::{BinRepr, Verifiable},
-    EnclaveReport,
+    cert::{parse_pem_chain, verify_chain, PCK},
+    collateral::Collateral,
+    enclave::EnclaveIdStatus,
+    error::{AtStep, DcapError, Result, StepResult, VerificationStep},
+    quote::{EnclaveReport, QuoteBody, ReportBody},
+    report::VerificationReport,
+    tcb_info::TcbStatus,
+    traits::{BinRepr, StepVerifiable, Verifiable},
 };
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QECertData {
     pub cert_type: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub cert_data: Vec<u8>,
 }
 
 impl BinRepr for QECertData {
     fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        if bytes.len() < 6 {
-            return Err(anyhow!("Invalid length for QE Cert Data"));
-        }
-        let cert_type = u16::from_le_bytes([bytes[0], bytes[1]]);
-        let cert_size = u32::from_le_bytes(bytes[2..6].try_into()?);
-
-        let bytes = &bytes[6..];
-        if bytes.len() != cert_size as usize {
-            return Err(anyhow!("Mismatched cert size"));
-        }
-
+        let (cert_type, cert_data) = parse_cert_data(bytes)?;
         Ok(QECertData {
             cert_type,
-            cert_data: bytes.to_vec(),
+            cert_data: cert_data.to_vec(),
         })
     }
 
@@ -51,79 +47,243 @@ impl BinRepr for QECertData {
 
 impl QECertData {
     pub fn certs(&self) -> Result<[Certificate; 3]> {
-        let ret = pem::parse_many(&self.cert_data)?
-            .iter()
-            .map(|pem| Ok(Certificate::from_der(pem.contents())?))
-            .collect::<Result<Vec<_>>>()?;
-        ret.try_into()
-            .map_err(|_| anyhow!("Should be exact 3 certificates."))
+        parse_cert_chain(&self.cert_data)
     }
 }
 
+/// Split QE certification data into its type and the data it borrows.
+fn parse_cert_data(bytes: &[u8]) -> Result<(u16, &[u8])> {
+    if bytes.len() < 6 {
+        return Err(DcapError::Parse("Invalid length for QE Cert Data".into()));
+    }
+    let cert_type = u16::from_le_bytes([bytes[0], bytes[1]]);
+    let cert_size = u32::from_le_bytes(bytes[2..6].try_into()?);
+
+    let bytes = &bytes[6..];
+    if bytes.len() != cert_size as usize {
+        return Err(DcapError::Parse("Mismatched cert size".into()));
+    }
+    Ok((cert_type, bytes))
+}
+
+fn parse_cert_chain(cert_data: &[u8]) -> Result<[Certificate; 3]> {
+    parse_pem_chain(cert_data)?
+        .try_into()
+        .map_err(|_| DcapError::Parse("Should be exact 3 certificates.".into()))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ECDSAQuoteV3AuthData {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub ecdsa256_bit_signature: [u8; 64],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub ecdsa_attestation_key: [u8; 64],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub raw_qe_report: [u8; 384],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub qe_report_signature: [u8; 64],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub qe_auth_data: Vec<u8>,
     pub qe_cert: QECertData,
 }
 
 impl ECDSAQuoteV3AuthData {
-    pub fn qe_report(&self) -> EnclaveReport {
-        EnclaveReport::from_bytes(self.raw_qe_report.as_slice()).unwrap()
+    pub fn qe_report(&self) -> Result<EnclaveReport> {
+        self.view().qe_report()
+    }
+
+    /// Borrow the signature data, e.g. to verify it against a signed body held elsewhere.
+    pub fn view(&self) -> AuthDataRef<'_> {
+        AuthDataRef {
+            ecdsa256_bit_signature: &self.ecdsa256_bit_signature,
+            ecdsa_attestation_key: &self.ecdsa_attestation_key,
+            raw_qe_report: &self.raw_qe_report,
+            qe_report_signature: &self.qe_report_signature,
+            qe_auth_data: &self.qe_auth_data,
+            cert_type: self.qe_cert.cert_type,
+            cert_data: &self.qe_cert.cert_data,
+        }
+    }
+
+    /// The four P-256 verifications of the signature data: the PCK Platform CA and PCK
+    /// certificates, the QE report and the quote body, each with its signer's key.
+    pub fn ecdsa_params(&self, body: &QuoteBody) -> Result<[EcdsaParams; 4]> {
+        self.view().ecdsa_params(&body.to_bytes()?)
+    }
+
+    /// Verify the QE report and PCK chain against `collateral` as of `now`, then the
+    /// attestation signature over `body`. Shared by every quote version.
+    fn verify_signature_data(
+        &self,
+        (body, collateral, now): &(&QuoteBody, &Collateral, DateTime<Utc>),
+    ) -> StepResult<VerificationReport> {
+        let signed_body = body.to_bytes().at(VerificationStep::QuoteSignature)?;
+        self.view()
+            .verify_signature_data(&body.report, &signed_body, collateral, *now)
     }
 }
 
-impl BinRepr for ECDSAQuoteV3AuthData {
-    fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        if bytes.len() < 576 {
-            return Err(anyhow!("Invalid length for ECDSA Quote V3 Auth Data",));
+/// ECDSA signature data borrowed from the encoding of a quote, see [`QuoteRef`].
+///
+/// [`QuoteRef`]: crate::QuoteRef
+#[derive(Clone, Copy, Debug)]
+pub struct AuthDataRef<'a> {
+    pub ecdsa256_bit_signature: &'a [u8; 64],
+    pub ecdsa_attestation_key: &'a [u8; 64],
+    pub raw_qe_report: &'a [u8; 384],
+    pub qe_report_signature: &'a [u8; 64],
+    pub qe_auth_data: &'a [u8],
+    pub cert_type: u16,
+    /// PEM encoded PCK certificate chain.
+    pub cert_data: &'a [u8],
+}
+
+impl<'a> AuthDataRef<'a> {
+    /// Borrow the signature data of a v3 quote.
+    pub fn from_v3_bytes(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < 578 {
+            return Err(DcapError::Parse(
+                "Invalid length for ECDSA Quote V3 Auth Data".into(),
+            ));
         }
-        let ecdsa256_bit_signature: [u8; 64] = bytes[0..64].try_into()?;
-        let ecdsa_attestation_key: [u8; 64] = bytes[64..128].try_into()?;
-        let raw_qe_report: [u8; 384] = bytes[128..512].try_into()?;
-        let qe_report_signature: [u8; 64] = bytes[512..576].try_into()?;
-
-        let bytes = &bytes[576..];
-        let qu_auth_data_size = u16::from_le_bytes([bytes[0], bytes[1]]) as usize;
-
-        let bytes = &bytes[2..];
-        let qe_auth_data = bytes[..qu_auth_data_size].to_vec();
-
-        let qe_cert = QECertData::from_bytes(&bytes[qu_auth_data_size..])?;
-
-        Ok(ECDSAQuoteV3AuthData {
-            ecdsa256_bit_signature,
-            ecdsa_attestation_key,
-            raw_qe_report,
-            qe_report_signature,
-            qe_auth_data,
-            qe_cert,
+        Self::from_parts(&bytes[..128], &bytes[128..])
+    }
+
+    /// Borrow the signature data of a v4 or v5 quote, see [`ECDSAQuoteV4AuthData`].
+    pub fn from_v4_bytes(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < 134 {
+            return Err(DcapError::Parse(
+                "Invalid length for ECDSA Quote V4 Auth Data".into(),
+            ));
+        }
+        let cert_type = u16::from_le_bytes([bytes[128], bytes[129]]);
+        if cert_type != QE_REPORT_CERT_DATA_TYPE {
+            return Err(DcapError::Unsupported(format!(
+                "certification data type {}",
+                cert_type
+            )));
+        }
+        let cert_size = u32::from_le_bytes(bytes[130..134].try_into()?);
+        if bytes.len() - 134 != cert_size as usize {
+            return Err(DcapError::Parse("Mismatched cert size".into()));
+        }
+        if bytes.len() < 134 + 450 {
+            return Err(DcapError::Parse(
+                "Invalid length for QE Report Certification Data".into(),
+            ));
+        }
+        let auth_data = Self::from_parts(&bytes[..128], &bytes[134..])?;
+        // The QE Report Certification Data must in turn certify the QE with a PCK chain.
+        if auth_data.cert_type != PCK_CERT_CHAIN_TYPE {
+            return Err(DcapError::Unsupported(format!(
+                "QE Report Certification Data carrying certification data type {}",
+                auth_data.cert_type
+            )));
+        }
+        Ok(auth_data)
+    }
+
+    /// `signature` holds the attestation signature and key, `bytes` the QE report and what
+    /// follows it, laid out as in v3.
+    fn from_parts(signature: &'a [u8], bytes: &'a [u8]) -> Result<Self> {
+        let qu_auth_data_size = u16::from_le_bytes([bytes[448], bytes[449]]) as usize;
+
+        let rest = &bytes[450..];
+        if rest.len() < qu_auth_data_size {
+            return Err(DcapError::Parse("Invalid length for QE Auth Data".into()));
+        }
+        let (cert_type, cert_data) = parse_cert_data(&rest[qu_auth_data_size..])?;
+
+        Ok(Self {
+            ecdsa256_bit_signature: signature[0..64].try_into()?,
+            ecdsa_attestation_key: signature[64..128].try_into()?,
+            raw_qe_report: bytes[0..384].try_into()?,
+            qe_report_signature: bytes[384..448].try_into()?,
+            qe_auth_data: &rest[..qu_auth_data_size],
+            cert_type,
+            cert_data,
         })
     }
 
-    fn to_bytes(&self) -> Result<Vec<u8>> {
-        let mut bytes = vec![];
-        bytes.extend_from_slice(&self.ecdsa256_bit_signature);
-        bytes.extend_from_slice(&self.ecdsa_attestation_key);
-        bytes.extend_from_slice(&self.raw_qe_report);
-        bytes.extend_from_slice(&self.qe_report_signature);
-        bytes.extend_from_slice(&(self.qe_auth_data.len() as u16).to_le_bytes());
-        bytes.extend_from_slice(&self.qe_auth_data);
-        bytes.extend_from_slice(&self.qe_cert.to_bytes()?);
-        Ok(bytes)
+    /// Copy the signature data out of the quote.
+    pub fn into_owned(self) -> ECDSAQuoteV3AuthData {
+        ECDSAQuoteV3AuthData {
+            ecdsa256_bit_signature: *self.ecdsa256_bit_signature,
+            ecdsa_attestation_key: *self.ecdsa_attestation_key,
+            raw_qe_report: *self.raw_qe_report,
+            qe_report_signature: *self.qe_report_signature,
+            qe_auth_data: self.qe_auth_data.to_vec(),
+            qe_cert: QECertData {
+                cert_type: self.cert_type,
+                cert_data: self.cert_data.to_vec(),
+            },
+        }
     }
-}
 
-impl Verifiable for ECDSAQuoteV3AuthData {
-    type Output = [EcdsaParams; 4];
-    type Payload = Vec<u8>;
+    pub fn qe_report(&self) -> Result<EnclaveReport> {
+        EnclaveReport::from_bytes(self.raw_qe_report)
+    }
+
+    pub fn certs(&self) -> Result<[Certificate; 3]> {
+        parse_cert_chain(self.cert_data)
+    }
+
+    /// The four P-256 verifications of the signature data: the PCK Platform CA and PCK
+    /// certificates, the QE report and the quote body, each with its signer's key.
+    pub fn ecdsa_params(&self, signed_body: &[u8]) -> Result<[EcdsaParams; 4]> {
+        let [pck, ca, root] = self.certs()?;
+        Ok([
+            (
+                VerifyingKey::from_spki(&root.tbs_certificate.subject_public_key_info)?,
+                Signature::from_der(ca.signature.raw_bytes())
+                    .map_err(|e| DcapError::Parse(e.to_string()))?
+                    .to_bytes(),
+                ca.tbs_certificate.to_der()?,
+            )
+                .into(),
+            (
+                VerifyingKey::from_spki(&ca.tbs_certificate.subject_public_key_info)?,
+                Signature::from_der(pck.signature.raw_bytes())
+                    .map_err(|e| DcapError::Parse(e.to_string()))?
+                    .to_bytes(),
+                pck.tbs_certificate.to_der()?,
+            )
+                .into(),
+            (
+                VerifyingKey::from_spki(&pck.tbs_certificate.subject_public_key_info)?,
+                self.qe_report_signature,
+                self.raw_qe_report,
+            )
+                .into(),
+            (
+                VerifyingKey::from_untagged_bytes(self.ecdsa_attestation_key)?,
+                self.ecdsa256_bit_signature,
+                signed_body,
+            )
+                .into(),
+        ])
+    }
+
+    /// Verify the QE report and PCK chain against `collateral` as of `now`, then the
+    /// attestation signature over `signed_body`, the encoded quote body carrying `report`.
+    pub(crate) fn verify_signature_data(
+        &self,
+        report: &ReportBody,
+        signed_body: &[u8],
+        collateral: &Collateral,
+        now: DateTime<Utc>,
+    ) -> StepResult<VerificationReport> {
+        use VerificationStep::*;
+
+        let td_report = report.td_report();
 
-    fn verify(&self, payload: &Self::Payload) -> Result<()> {
         // STEP3: Verify the Enclave ID
-        let qe_report = self.qe_report();
-        let enclave_id = EnclaveId::get();
+        let qe_report = self.qe_report().at(QeIdentity)?;
+        let enclave_id = match td_report {
+            Some(_) => collateral.td_qe_identity().at(QeIdentity)?,
+            None => collateral.qe_identity(),
+        };
+        enclave_id.verify_validity(now).at(QeIdentity)?;
 
         let miscselect_matched =
             (qe_report.miscselect & enclave_id.miscselect_mask) == enclave_id.miscselect;
@@ -131,136 +291,301 @@ impl Verifiable for ECDSAQuoteV3AuthData {
             .zip(enclave_id.attributes.iter())
             .zip(enclave_id.attributes_mask.iter())
             .all(|((a, b), m)| (a & m) == (*b));
-        let mrsigner_matched = qe_report.mr_signer == enclave_id.mrsigner;
-        let tcb_found = enclave_id
-            .tcb_levels
-            .iter()
-            .find(|tcb| tcb.isvsvn <= qe_report.isv_svn)
-            .and_then(|tcb| {
-                if tcb.tcb_status == EnclaveIdStatus::OK {
-                    Some(tcb)
-                } else {
-                    None
-                }
-            })
-            .is_some();
-
-        if !(miscselect_matched && attributes_matched && mrsigner_matched && tcb_found) {
-            return Err(anyhow!("Enclave ID does not match"));
+        let mrsigner_matched = qe_report.mr_signer == enclave_id.mrsigner
+            && qe_report.isv_prod_id == enclave_id.isvprodid;
+        if !(miscselect_matched && attributes_matched && mrsigner_matched) {
+            return Err(DcapError::CollateralMismatch(
+                "QE report does not match the QE identity".into(),
+            ))
+            .at(QeIdentity);
+        }
+        // Like the platform, a QE below every known level must not be trusted.
+        let qe_tcb_level = enclave_id
+            .tcb_level(qe_report.isv_svn)
+            .ok_or(DcapError::TcbNotFound)
+            .at(QeIdentity)?;
+        if qe_tcb_level.tcb_status == EnclaveIdStatus::Revoked {
+            return Err(DcapError::TcbStatus(TcbStatus::Revoked)).at(QeIdentity);
         }
 
         // STEP4: Parse quote cert  chain
-        let [pck, ca, root] = self.qe_cert.certs()?;
-        let pck = PCK::new(pck);
+        let [pck, ca, root] = self.certs().at(PckChainParse)?;
+        let pck = PCK::new(pck).at(PckChainParse)?;
 
         //STEP5: pck check
-        let tcb_info = TcbInfo::get();
-        if tcb_info.fmspc != pck.fmspc || tcb_info.pce_id != pck.pce_id {
-            return Err(anyhow!("Unmatched tcb"));
+        let (fmspc, pce_id) = (&pck.extensions.fmspc, &pck.extensions.pce_id);
+        let tcb_info = match td_report {
+            Some(_) => collateral.tdx_tcb_info(fmspc, pce_id),
+            None => collateral.tcb_info(fmspc, pce_id),
+        }
+        .at(PckCollateral)?;
+        tcb_info.verify_validity(now).at(PckCollateral)?;
+
+        // The TD report must come from a TDX module signed by Intel. TCB Info v3 identifies
+        // each major version of the module separately, together with its own TCB levels.
+        let tdx_module_version = td_report
+            .map(|td_report| td_report.tee_tcb_svn[1])
+            .filter(|version| *version > 0 && !tcb_info.tdx_module_identities.is_empty());
+        let mut tdx_module_tcb_level = None;
+        if let Some(td_report) = td_report {
+            let tdx_module = match tdx_module_version {
+                Some(version) => {
+                    let identity = tcb_info
+                        .tdx_module_identity(version)
+                        .ok_or_else(|| {
+                            DcapError::CollateralMismatch(format!(
+                                "TCB Info has no identity for TDX module version {}",
+                                version
+                            ))
+                        })
+                        .at(PckCollateral)?;
+                    // The minor version at index 0 of the TEE TCB SVN selects the TCB level.
+                    let level = identity
+                        .tcb_levels
+                        .iter()
+                        .find(|level| level.isvsvn <= td_report.tee_tcb_svn[0])
+                        .ok_or(DcapError::TcbNotFound)
+                        .at(Tcb)?;
+                    if level.tcb_status == TcbStatus::Revoked {
+                        return Err(DcapError::TcbStatus(TcbStatus::Revoked)).at(Tcb);
+                    }
+                    tdx_module_tcb_level = Some(level.clone());
+                    &identity.module
+                }
+                None => tcb_info
+                    .tdx_module
+                    .as_ref()
+                    .ok_or_else(|| {
+                        DcapError::CollateralMismatch("TCB Info has no TDX module identity".into())
+                    })
+                    .at(PckCollateral)?,
+            };
+            if !tdx_module.matches(&td_report.mr_signer_seam, &td_report.seam_attributes) {
+                return Err(DcapError::CollateralMismatch(
+                    "TDX module does not match the TCB Info".into(),
+                ))
+                .at(PckCollateral);
+            }
         }
 
         //STEP6: TCB check
-        for tcb_level in tcb_info.tcb_levels.iter() {
-            let pce_svn_check = pck.tcb.pce_svn >= tcb_level.tcb.pcesvn;
+        // The first level met is the highest, see `TcbInfo::tcb_levels`.
+        let tcb_level = tcb_info.tcb_levels.iter().find(|tcb_level| {
+            let pce_svn_check = pck.extensions.tcb.pce_svn >= tcb_level.tcb.pcesvn;
             let cpu_svn_check = pck
+                .extensions
                 .tcb
                 .comp_svn_array
                 .iter()
-                .zip(tcb_level.tcb.sgxtcbcompsvn.iter())
+                .zip(tcb_level.tcb.sgxtcbcompsvn().iter())
                 .all(|(l, r)| l >= r);
-            if pce_svn_check && cpu_svn_check {
-                if &tcb_level.tcb_statue == "Revoked" {
-                    return Err(anyhow!("TCB is revoked"));
-                }
-                break;
-            }
+            // The TDX module version at the first two indexes has been matched against the
+            // module identity already.
+            let skip = if tdx_module_version.is_some() { 2 } else { 0 };
+            let tdx_svn_check = match (td_report, tcb_level.tcb.tdxtcbcompsvn()) {
+                (Some(td_report), Some(tdx_svn)) => (td_report.tee_tcb_svn.iter())
+                    .zip(tdx_svn.iter())
+                    .skip(skip)
+                    .all(|(l, r)| l >= r),
+                (Some(_), None) => false,
+                (None, _) => true,
+            };
+            pce_svn_check && cpu_svn_check && tdx_svn_check
+        });
+        // A platform below every known level has an unsupported TCB and must not be trusted.
+        let tcb_level = tcb_level.ok_or(DcapError::TcbNotFound).at(Tcb)?;
+        if tcb_level.tcb_status == TcbStatus::Revoked {
+            return Err(DcapError::TcbStatus(TcbStatus::Revoked)).at(Tcb);
         }
 
         // Verify the QE Report's Hash
         let hash = Sha256::new()
-            .chain_update(self.ecdsa_attestation_key.as_slice())
-            .chain_update(self.qe_auth_data.as_slice())
+            .chain_update(self.ecdsa_attestation_key)
+            .chain_update(self.qe_auth_data)
             .finalize();
-        let expected_hash = &self.qe_report().report_data[..32];
+        let expected_hash = &qe_report.report_data[..32];
         if hash.as_slice() != expected_hash {
-            return Err(anyhow!("QE Report's Hash is Invalid"));
+            return Err(DcapError::Signature(
+                "QE report data does not commit to the attestation key".into(),
+            ))
+            .at(QeReportSignature);
         }
 
         //STEP7: Verify cert chain
-        verify_signature(
-            &VerifyingKey::from_spki(&root.tbs_certificate.subject_public_key_info)?,
-            Signature::from_der(ca.signature.raw_bytes())
-                .unwrap()
-                .to_bytes(),
-            ca.tbs_certificate.to_der().unwrap(),
-        )?;
-
-        verify_signature(
-            &VerifyingKey::from_spki(&ca.tbs_certificate.subject_public_key_info)?,
-            Signature::from_der(pck.signature.raw_bytes())
-                .unwrap()
-                .to_bytes(),
-            pck.tbs_certificate.to_der().unwrap(),
-        )?;
+        // The collateral root is known to be self-signed, so matching it anchors the chain.
+        if &root != collateral.root_ca() {
+            return Err(DcapError::Chain("Root CA is not trusted".into())).at(PckChain);
+        }
+        let pck_key = pck.tbs_certificate.subject_public_key_info.clone();
+        let chain = [pck.into_inner(), ca, root];
+        verify_chain(&chain, now).at(PckChain)?;
+        collateral.verify_validity(now).at(PckChain)?;
+        collateral.verify_not_revoked(&chain).at(PckChain)?;
 
         // STEP8: Verify qe report sig
         // Verify the QE Report's Signature
         verify_signature(
-            &VerifyingKey::from_spki(&pck.tbs_certificate.subject_public_key_info)?,
+            &VerifyingKey::from_spki(&pck_key).at(QeReportSignature)?,
             self.qe_report_signature,
             self.raw_qe_report,
-        )?;
+        )
+        .at(QeReportSignature)?;
 
         // STEP9: Verify local attestation sig
         verify_signature(
-            &VerifyingKey::from_untagged_bytes(self.ecdsa_attestation_key)?,
+            &VerifyingKey::from_untagged_bytes(self.ecdsa_attestation_key).at(QuoteSignature)?,
             self.ecdsa256_bit_signature,
-            payload.as_slice(),
-        )?;
+            signed_body,
+        )
+        .at(QuoteSignature)?;
+
+        Ok(VerificationReport {
+            fmspc: tcb_info.fmspc,
+            tcb_level: tcb_level.clone(),
+            tdx_module_tcb_level,
+            qe_tcb_level: qe_tcb_level.clone(),
+        })
+    }
+}
 
-        Ok(())
+impl BinRepr for ECDSAQuoteV3AuthData {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(AuthDataRef::from_v3_bytes(bytes)?.into_owned())
     }
 
-    fn paramlized(&self, payload: &Self::Payload) -> Result<Self::Output> {
-        let [pck, ca, root] = self.qe_cert.certs()?;
-        Ok([
-            (
-                VerifyingKey::from_spki(&root.tbs_certificate.subject_public_key_info)?,
-                Signature::from_der(ca.signature.raw_bytes())
-                    .unwrap()
-                    .to_bytes(),
-                ca.tbs_certificate.to_der().unwrap(),
-            )
-                .into(),
-            (
-                VerifyingKey::from_spki(&ca.tbs_certificate.subject_public_key_info)?,
-                Signature::from_der(pck.signature.raw_bytes())
-                    .unwrap()
-                    .to_bytes(),
-                pck.tbs_certificate.to_der().unwrap(),
-            )
-                .into(),
-            (
-                VerifyingKey::from_spki(&pck.tbs_certificate.subject_public_key_info)?,
-                self.qe_report_signature,
-                self.raw_qe_report,
-            )
-                .into(),
-            (
-                VerifyingKey::from_untagged_bytes(self.ecdsa_attestation_key)?,
-                self.ecdsa256_bit_signature,
-                payload.as_slice(),
-            )
-                .into(),
-        ])
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&self.ecdsa256_bit_signature);
+        bytes.extend_from_slice(&self.ecdsa_attestation_key);
+        bytes.extend_from_slice(&self.raw_qe_report);
+        bytes.extend_from_slice(&self.qe_report_signature);
+        bytes.extend_from_slice(&(self.qe_auth_data.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&self.qe_auth_data);
+        bytes.extend_from_slice(&self.qe_cert.to_bytes()?);
+        Ok(bytes)
+    }
+}
+
+/// Check that the signed quote body carries the version of the signature data parsing it.
+fn verify_quote_version(body: &QuoteBody, versions: &[u16]) -> Result<()> {
+    if !versions.contains(&body.header.version) {
+        return Err(DcapError::Unsupported(format!(
+            "quote version {} with this signature data",
+            body.header.version
+        )));
+    }
+    Ok(())
+}
+
+impl StepVerifiable for ECDSAQuoteV3AuthData {
+    fn verify_steps(&self, payload: &Self::Payload<'_>) -> StepResult<Self::Report> {
+        verify_quote_version(payload.0, &[3]).at(VerificationStep::Header)?;
+        self.verify_signature_data(payload)
     }
 }
 
-#[cfg(test)]
+impl Verifiable for ECDSAQuoteV3AuthData {
+    type Output = [EcdsaParams; 4];
+    type Report = VerificationReport;
+    type Payload<'a> = (&'a QuoteBody, &'a Collateral, DateTime<Utc>);
+
+    fn verify(&self, payload: &Self::Payload<'_>) -> Result<Self::Report> {
+        Ok(self.verify_steps(payload)?)
+    }
+
+    fn paramlized(&self, (body, _, _): &Self::Payload<'_>) -> Result<Self::Output> {
+        self.ecdsa_params(body)
+    }
+}
+
+/// Certification data type of a QE Report Certification Data block.
+const QE_REPORT_CERT_DATA_TYPE: u16 = 6;
+/// Certification data type of a PEM encoded PCK certificate chain.
+const PCK_CERT_CHAIN_TYPE: u16 = 5;
+
+/// ECDSA signature data of a v4 or v5 quote.
+///
+/// The QE report, its signature, the QE authentication data and the PCK chain are laid out as
+/// in v3, but nested in a QE Report Certification Data (type 6) block:
+///     ecdsa256_bit_signature: 64bytes
+///     ecdsa_attestation_key: 64bytes
+///     cert_type: 2bytes (6)
+///     cert_size: 4bytes
+///     raw_qe_report: 384bytes
+///     qe_report_signature: 64bytes
+///     qe_auth_data_size: 2bytes
+///     qe_auth_data: qe_auth_data_size
+///     qe_cert: PCK cert chain (type 5)
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ECDSAQuoteV4AuthData {
+    inner: ECDSAQuoteV3AuthData,
+}
+
+impl core::ops::Deref for ECDSAQuoteV4AuthData {
+    type Target = ECDSAQuoteV3AuthData;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl ECDSAQuoteV4AuthData {
+    pub fn new(inner: ECDSAQuoteV3AuthData) -> Self {
+        Self { inner }
+    }
+
+    pub fn into_inner(self) -> ECDSAQuoteV3AuthData {
+        self.inner
+    }
+}
+
+impl BinRepr for ECDSAQuoteV4AuthData {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(Self::new(AuthDataRef::from_v4_bytes(bytes)?.into_owned()))
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let v3 = self.inner.to_bytes()?;
+        let (signature, cert_data) = v3.split_at(128);
+
+        let mut bytes = Vec::with_capacity(v3.len() + 6);
+        bytes.extend_from_slice(signature);
+        bytes.extend_from_slice(&QE_REPORT_CERT_DATA_TYPE.to_le_bytes());
+        bytes.extend_from_slice(&(cert_data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(cert_data);
+        Ok(bytes)
+    }
+}
+
+impl StepVerifiable for ECDSAQuoteV4AuthData {
+    fn verify_steps(&self, payload: &Self::Payload<'_>) -> StepResult<Self::Report> {
+        // The signature data is laid out the same way in v5 quotes.
+        verify_quote_version(payload.0, &[4, 5]).at(VerificationStep::Header)?;
+        self.inner.verify_signature_data(payload)
+    }
+}
+
+impl Verifiable for ECDSAQuoteV4AuthData {
+    type Output = [EcdsaParams; 4];
+    type Report = VerificationReport;
+    type Payload<'a> = (&'a QuoteBody, &'a Collateral, DateTime<Utc>);
+
+    fn verify(&self, payload: &Self::Payload<'_>) -> Result<Self::Report> {
+        Ok(self.verify_steps(payload)?)
+    }
+
+    fn paramlized(&self, payload: &Self::Payload<'_>) -> Result<Self::Output> {
+        self.inner.paramlized(payload)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
+    use anyhow::Result;
     use hex_literal::hex;
 
     use super::*;
+    use crate::cert::{PlatformConfiguration, SgxType};
 
     const RAW: [u8; 4298] = hex!("84af1f392be216944059f3fa05bf91e1b4e9b513c67493521eb4488af35f49c8f300d57955afc1df97d423c8718ed5b0af82f71047a229df221faa6817ad5daa44131b5c2ed877295959f7333543ba3f17994d767da194a27ba7a4e8a71940118a138dce8499572433c2cc4e4312f92e7144b26f84c59022bfc9aea59967f00d0c0c100fffff0100000000000000000000000000000000000000000000000000000000000000000000000000000000001500000000000000e700000000000000192aa50ce1c0cef03ccf89e7b5b16b0d7978f5c2b1edcf774d87702e8154d8bf00000000000000000000000000000000000000000000000000000000000000008c4f5775d796503e96137f77c68a829a0056ac8ded70140b081b094490c57bff00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000900000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000a654bcd78ffaa5cfc888fc90cbc24fb7f6e19bc8661671f1e3b2cc947db3b6340000000000000000000000000000000000000000000000000000000000000000839adce904d2aec1fc021ad0ec370c7176942d4b64939b95a2e1e1d3e09bf2e57093231f4308b64e8f53b81cd6ae36fc52f202e66ac77b93b13307ee577be36b2000000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f0500620e00002d2d2d2d2d424547494e2043455254494649434154452d2d2d2d2d0a4d494945386a4343424a696741774942416749554b6e314f2b2b58517264456161433535634a4c307470464867336b77436759494b6f5a497a6a3045417749770a634445694d434147413155454177775a535735305a577767553064594946424453794251624746305a6d397962534244515445614d42674741315545436777520a535735305a577767513239796347397959585270623234784644415342674e564241634d43314e68626e526849454e7359584a684d51737743515944565151490a44414a445154454c4d416b474131554542684d4356564d774868634e4d6a4d774f4449304d6a45304d444d775768634e4d7a41774f4449304d6a45304d444d770a576a42774d534977494159445651514444426c4a626e526c624342545231676755454e4c49454e6c636e52705a6d6c6a5958526c4d526f77474159445651514b0a4442464a626e526c6243424462334a7762334a6864476c76626a45554d424947413155454277774c553246756447456751327868636d4578437a414a42674e560a4241674d416b4e424d517377435159445651514745774a56557a425a4d424d4742797147534d34394167454743437147534d34394177454841304941424e47520a727a716c416d4a66617756324b67656a39576e774a736666457868445631756847396e6d57377430505a646e6276732f6c677872584255625657436d5043456f0a4f49587768563673736d6e6b6b48462b576d536a67674d4f4d494944436a416642674e5648534d4547444157674253566231334e765276683655424a796454300a4d383442567776655644427242674e56485238455a4442694d47436758714263686c706f64485277637a6f764c32467761533530636e567a6447566b633256790a646d6c6a5a584d75615735305a577775593239744c334e6e6543396a5a584a3061575a7059324630615739754c33597a4c33426a61324e796244396a595431770a624746305a6d397962535a6c626d4e765a476c755a7a316b5a584977485159445652304f424259454641337234524b62476e54316e584c775a5a7272515559410a4a6b776c4d41344741315564447745422f775145417749477744414d42674e5648524d4241663845416a41414d4949434f77594a4b6f5a496876684e415130420a424949434c444343416967774867594b4b6f5a496876684e415130424151515179753373424e6d7632566643337932772f445344627a434341575547436971470a534962345451454e41514977676746564d42414743797147534962345451454e415149424167454d4d42414743797147534962345451454e415149434167454d0a4d42414743797147534962345451454e41514944416745444d42414743797147534962345451454e41514945416745444d42454743797147534962345451454e0a41514946416749412f7a415242677371686b69472b4530424451454342674943415038774541594c4b6f5a496876684e4151304241676343415145774541594c0a4b6f5a496876684e4151304241676743415141774541594c4b6f5a496876684e4151304241676b43415141774541594c4b6f5a496876684e4151304241676f430a415141774541594c4b6f5a496876684e4151304241677343415141774541594c4b6f5a496876684e4151304241677743415141774541594c4b6f5a496876684e0a4151304241673043415141774541594c4b6f5a496876684e4151304241673443415141774541594c4b6f5a496876684e4151304241673843415141774541594c0a4b6f5a496876684e4151304241684143415141774541594c4b6f5a496876684e4151304241684543415130774877594c4b6f5a496876684e41513042416849450a4541774d4177502f2f7745414141414141414141414141774541594b4b6f5a496876684e4151304241775143414141774641594b4b6f5a496876684e415130420a4241514741474271414141414d41384743697147534962345451454e4151554b415145774867594b4b6f5a496876684e415130424267515136657645326f42790a6f684e362f30727741346d642b6a424542676f71686b69472b453042445145484d4459774541594c4b6f5a496876684e4151304242774542416638774541594c0a4b6f5a496876684e4151304242774942415141774541594c4b6f5a496876684e4151304242774d4241514177436759494b6f5a497a6a304541774944534141770a52514967522b344377346437476a73684848436c7a394c6269785a4a45632f31666c7a734449504d5451437a2b43304349514430516e6d514c2b4e6b4e374a7a0a655a666c5078644734687a374b652b3443595366744b416a48545a7539413d3d0a2d2d2d2d2d454e442043455254494649434154452d2d2d2d2d0a2d2d2d2d2d424547494e2043455254494649434154452d2d2d2d2d0a4d4949436c6a4343416a32674177494241674956414a567658633239472b487051456e4a3150517a7a674658433935554d416f4743437147534d343942414d430a4d476778476a415942674e5642414d4d45556c756447567349464e48574342536232393049454e424d526f77474159445651514b4442464a626e526c624342440a62334a7762334a6864476c76626a45554d424947413155454277774c553246756447456751327868636d4578437a414a42674e564241674d416b4e424d5173770a435159445651514745774a56557a4165467730784f4441314d6a45784d4455774d5442614677307a4d7a41314d6a45784d4455774d5442614d484178496a41670a42674e5642414d4d47556c756447567349464e4857434251513073675547786864475a76636d306751304578476a415942674e5642416f4d45556c75644756730a49454e76636e4276636d4630615739754d5251774567594456515148444174545957353059534244624746795954454c4d416b474131554543417743513045780a437a414a42674e5642415954416c56544d466b77457759484b6f5a497a6a3043415159494b6f5a497a6a304441516344516741454e53422f377432316c58534f0a3243757a7078773734654a423732457944476757357258437478327456544c7136684b6b367a2b5569525a436e71523770734f766771466553786c6d546c4a6c0a65546d693257597a33714f42757a43427544416642674e5648534d4547444157674251695a517a575770303069664f44744a5653763141624f536347724442530a42674e5648523845537a424a4d45656752614244686b466f64485277637a6f764c324e6c636e52705a6d6c6a5958526c63793530636e567a6447566b633256790a646d6c6a5a584d75615735305a577775593239744c306c756447567355306459556d397664454e424c6d526c636a416442674e5648513445466751556c5739640a7a62306234656c4153636e553944504f4156634c336c517744675944565230504151482f42415144416745474d42494741315564457745422f7751494d4159420a4166384341514177436759494b6f5a497a6a30454177494452774177524149675873566b6930772b6936565947573355462f32327561586530594a446a3155650a6e412b546a44316169356343494359623153416d4435786b66545670766f34556f79695359787244574c6d5552344349394e4b7966504e2b0a2d2d2d2d2d454e442043455254494649434154452d2d2d2d2d0a2d2d2d2d2d424547494e2043455254494649434154452d2d2d2d2d0a4d4949436a7a4343416a53674177494241674955496d554d316c71644e496e7a6737535655723951477a6b6e42717777436759494b6f5a497a6a3045417749770a614445614d4267474131554541777752535735305a5777675530645949464a766233516751304578476a415942674e5642416f4d45556c756447567349454e760a636e4276636d4630615739754d5251774567594456515148444174545957353059534244624746795954454c4d416b47413155454341774351304578437a414a0a42674e5642415954416c56544d423458445445344d4455794d5445774e4455784d466f58445451354d54497a4d54497a4e546b314f566f77614445614d4267470a4131554541777752535735305a5777675530645949464a766233516751304578476a415942674e5642416f4d45556c756447567349454e76636e4276636d46300a615739754d5251774567594456515148444174545957353059534244624746795954454c4d416b47413155454341774351304578437a414a42674e56424159540a416c56544d466b77457759484b6f5a497a6a3043415159494b6f5a497a6a3044415163445167414543366e45774d4449595a4f6a2f69505773437a61454b69370a314f694f534c52466857476a626e42564a66566e6b59347533496a6b4459594c304d784f346d717379596a6c42616c54565978465032734a424b357a6c4b4f420a757a43427544416642674e5648534d4547444157674251695a517a575770303069664f44744a5653763141624f5363477244425342674e5648523845537a424a0a4d45656752614244686b466f64485277637a6f764c324e6c636e52705a6d6c6a5958526c63793530636e567a6447566b63325679646d6c6a5a584d75615735300a5a577775593239744c306c756447567355306459556d397664454e424c6d526c636a416442674e564851344546675155496d554d316c71644e496e7a673753560a55723951477a6b6e4271777744675944565230504151482f42415144416745474d42494741315564457745422f7751494d4159424166384341514577436759490a4b6f5a497a6a3045417749445351417752674968414f572f35516b522b533943695344634e6f6f774c7550524c735747662f59693747535839344267775477670a41694541344a306c72486f4d732b586f356f2f7358364f39515778485241765a55474f6452513763767152586171493d0a2d2d2d2d2d454e442043455254494649434154452d2d2d2d2d0a00");
 
@@ -271,4 +596,58 @@ mod tests {
         assert_eq!(RAW.as_slice(), bytes.as_slice());
         Ok(())
     }
+
+    #[test]
+    fn test_ecdsa_sig_truncated() {
+        for len in 0..RAW.len() {
+            assert!(ECDSAQuoteV3AuthData::from_bytes(&RAW[..len]).is_err());
+        }
+        let mut oversized = RAW.to_vec();
+        oversized.push(0);
+        assert!(ECDSAQuoteV3AuthData::from_bytes(&oversized).is_err());
+    }
+
+    #[test]
+    fn test_pck_sgx_extensions() -> Result<()> {
+        let sig = ECDSAQuoteV3AuthData::from_bytes(&RAW)?;
+        let [pck, ca, _] = sig.qe_cert.certs()?;
+        let pck = PCK::new(pck)?;
+        assert_eq!(pck.extensions.fmspc, hex!("00606a000000"));
+        assert_eq!(pck.extensions.pce_id, hex!("0000"));
+        assert_eq!(pck.extensions.tcb.pce_svn, 13);
+        assert_eq!(pck.extensions.cpu_svn, pck.extensions.tcb.comp_svn_array);
+        assert_eq!(
+            pck.extensions.ppid,
+            hex!("caedec04d9afd957c2df2db0fc34836f")
+        );
+        // Issued by the Platform CA to a multi-package platform.
+        assert_eq!(pck.extensions.sgx_type, SgxType::Scalable);
+        assert_eq!(
+            pck.extensions.platform_instance_id,
+            Some(hex!("e9ebc4da8072a2137aff4af003899dfa"))
+        );
+        assert_eq!(
+            pck.extensions.configuration,
+            Some(PlatformConfiguration {
+                dynamic_platform: Some(true),
+                cached_keys: Some(false),
+                smt_enabled: Some(false),
+            })
+        );
+
+        assert!(matches!(PCK::new(ca), Err(DcapError::Parse(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pck_chain_validity() -> Result<()> {
+        let sig = ECDSAQuoteV3AuthData::from_bytes(&RAW)?;
+        let chain = sig.qe_cert.certs()?;
+
+        // The PCK certificate is valid from 2023-08-24 until 2030-08-24.
+        verify_chain(&chain, "2024-01-01T00:00:00Z".parse()?)?;
+        assert!(verify_chain(&chain, "2023-01-01T00:00:00Z".parse()?).is_err());
+        assert!(verify_chain(&chain, "2031-01-01T00:00:00Z".parse()?).is_err());
+        Ok(())
+    }
 }
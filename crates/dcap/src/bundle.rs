@@ -0,0 +1,367 @@
+use alloc::{format, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use x509_cert::der::{pem::LineEnding, EncodePem};
+use x509_cert::Certificate;
+
+use super::{
+    cert::{intel_root_ca, parse_pem_chain},
+    collateral::Collateral,
+    error::{DcapError, Result},
+};
+
+/// Everything needed to verify quotes without network access, in a single JSON document.
+///
+/// PCS documents are kept as the exact strings served by the PCS, since their signatures
+/// cover the raw bytes:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "root_ca": "-----BEGIN CERTIFICATE-----...",
+///   "tcb_signing_chain": "-----BEGIN CERTIFICATE-----...",
+///   "tcb_infos": ["{\"tcbInfo\":{...},\"signature\":\"...\"}"],
+///   "qe_identity": "{\"enclaveIdentity\":{...},\"signature\":\"...\"}",
+///   "td_qe_identity": null,
+///   "root_ca_crl": "308201...",
+///   "pck_crls": [{ "crl": "-----BEGIN X509 CRL-----...", "issuer_chain": "-----BEGIN..." }]
+/// }
+/// ```
+///
+/// CRLs are PEM or hex encoded DER, as served by the PCS `pckcrl` and `rootcacrl` endpoints.
+/// Quotes only verify if the bundle holds the CRL of every CA of their PCK chain, see
+/// [`Collateral::allow_missing_crls`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CollateralBundle {
+    pub version: u32,
+    /// PEM encoded root CA the bundle was fetched under. Only a root the verifier trusts is
+    /// accepted when loading.
+    pub root_ca: String,
+    /// PEM encoded `TCB-Info-Issuer-Chain`.
+    pub tcb_signing_chain: String,
+    /// SGX and TDX TCB Info documents, at most one of each kind per FMSPC and PCE ID.
+    pub tcb_infos: Vec<String>,
+    pub qe_identity: String,
+    /// Required to verify TDX quotes.
+    #[serde(default)]
+    pub td_qe_identity: Option<String>,
+    #[serde(default)]
+    pub root_ca_crl: Option<String>,
+    #[serde(default)]
+    pub pck_crls: Vec<PckCrl>,
+}
+
+/// A PCK CRL with the PEM encoded `SGX-PCK-CRL-Issuer-Chain` returned alongside it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PckCrl {
+    pub crl: String,
+    pub issuer_chain: String,
+}
+
+impl CollateralBundle {
+    pub const VERSION: u32 = 1;
+
+    /// Parse a bundle, rejecting format versions this crate does not know.
+    pub fn load(raw: impl AsRef<[u8]>) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Versioned {
+            version: u32,
+        }
+
+        let Versioned { version } = serde_json::from_slice(raw.as_ref())?;
+        if version != Self::VERSION {
+            return Err(DcapError::Unsupported(format!(
+                "collateral bundle version {}",
+                version
+            )));
+        }
+        Ok(serde_json::from_slice(raw.as_ref())?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Verify every document of the bundle and build collateral anchored at the Intel SGX
+    /// Root CA.
+    pub fn collateral(&self) -> Result<Collateral> {
+        self.collateral_with_root_ca(intel_root_ca())
+    }
+
+    /// Same as [`CollateralBundle::collateral`] but anchored at `root_ca`, which the bundled
+    /// root CA must match.
+    pub fn collateral_with_root_ca(&self, root_ca: Certificate) -> Result<Collateral> {
+        if parse_pem_chain(&self.root_ca)? != [root_ca.clone()] {
+            return Err(DcapError::Chain(
+                "Bundled root CA is not the trusted root CA".into(),
+            ));
+        }
+
+        let mut collateral = Collateral::with_root_ca(
+            root_ca,
+            &self.tcb_infos,
+            &self.qe_identity,
+            &self.tcb_signing_chain,
+        )?;
+        if let Some(td_qe_identity) = &self.td_qe_identity {
+            collateral.set_td_qe_identity(td_qe_identity)?;
+        }
+        if let Some(crl) = &self.root_ca_crl {
+            collateral.add_root_ca_crl(decode_crl(crl)?)?;
+        }
+        for pck_crl in &self.pck_crls {
+            collateral.add_pck_crl(decode_crl(&pck_crl.crl)?, &pck_crl.issuer_chain)?;
+        }
+        Ok(collateral)
+    }
+}
+
+/// Collateral bundles can also be kept as a directory of PCS documents, one per file:
+///
+/// | File                           | Content                                           |
+/// |--------------------------------|---------------------------------------------------|
+/// | `root_ca.pem`                  | Root CA, optional, defaults to the trusted root   |
+/// | `tcb_signing_chain.pem`        | `TCB-Info-Issuer-Chain`                           |
+/// | `tcb_info*.json`               | SGX and TDX TCB Info                              |
+/// | `qe_identity.json`             | QE Identity                                       |
+/// | `td_qe_identity.json`          | TD QE Identity, optional                          |
+/// | `root_ca_crl.*`                | Root CA CRL in PEM, DER or hex                    |
+/// | `pck_crl*.*`                   | PCK CRLs in PEM, DER or hex                       |
+/// | `pck_crl*_issuer_chain.pem`    | `SGX-PCK-CRL-Issuer-Chain` of the CRL of the name |
+///
+/// E.g. `pck_crl.der` comes with `pck_crl_issuer_chain.pem`.
+#[cfg(feature = "std")]
+impl CollateralBundle {
+    /// Load a bundle file, or assemble a bundle from a directory laid out as above. Only a
+    /// directory without `root_ca.pem` is bundled under `root_ca`.
+    pub fn load_path(path: impl AsRef<Path>, root_ca: &Certificate) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Self::load(read(path)?);
+        }
+
+        let mut entries = fs::read_dir(path)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| Ok(entry?.path()))
+                    .collect::<std::io::Result<Vec<_>>>()
+            })
+            .map_err(|err| read_error(path, err))?;
+        entries.sort();
+
+        let mut tcb_infos = Vec::new();
+        let mut root_ca_crl = None;
+        let mut pck_crls = Vec::new();
+        for entry in entries {
+            let name = entry.file_name().unwrap_or_default().to_string_lossy();
+            let stem = entry.file_stem().unwrap_or_default().to_string_lossy();
+            if name.starts_with("tcb_info") && name.ends_with(".json") {
+                tcb_infos.push(read_string(&entry)?);
+            } else if stem == "root_ca_crl" {
+                root_ca_crl = Some(read_crl(&entry)?);
+            } else if stem.starts_with("pck_crl") && !stem.ends_with("_issuer_chain") {
+                pck_crls.push(PckCrl {
+                    crl: read_crl(&entry)?,
+                    issuer_chain: read_string(&path.join(format!("{}_issuer_chain.pem", stem)))?,
+                });
+            }
+        }
+        if tcb_infos.is_empty() {
+            return Err(DcapError::Parse(format!(
+                "No tcb_info*.json in {}",
+                path.display()
+            )));
+        }
+
+        let root_ca = match read_optional(&path.join("root_ca.pem"))? {
+            Some(root_ca) => root_ca,
+            None => root_ca.to_pem(LineEnding::LF)?,
+        };
+        Ok(Self {
+            version: Self::VERSION,
+            root_ca,
+            tcb_signing_chain: read_string(&path.join("tcb_signing_chain.pem"))?,
+            tcb_infos,
+            qe_identity: read_string(&path.join("qe_identity.json"))?,
+            td_qe_identity: read_optional(&path.join("td_qe_identity.json"))?,
+            root_ca_crl,
+            pck_crls,
+        })
+    }
+
+    /// Write the bundle to the directory `path` in the layout [`CollateralBundle::load_path`]
+    /// reads, creating it if needed.
+    pub fn save_dir(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let write = |name: &str, contents: &str| {
+            let file = path.join(name);
+            fs::write(&file, contents).map_err(|err| write_error(&file, err))
+        };
+        // PEM CRLs are kept as they are, the others are hex encoded.
+        let crl_name = |stem: &str, crl: &str| {
+            if crl.trim().starts_with("-----BEGIN") {
+                format!("{}.pem", stem)
+            } else {
+                format!("{}.hex", stem)
+            }
+        };
+
+        fs::create_dir_all(path).map_err(|err| write_error(path, err))?;
+        write("root_ca.pem", &self.root_ca)?;
+        write("tcb_signing_chain.pem", &self.tcb_signing_chain)?;
+        for (i, tcb_info) in self.tcb_infos.iter().enumerate() {
+            write(&format!("tcb_info_{:02}.json", i), tcb_info)?;
+        }
+        write("qe_identity.json", &self.qe_identity)?;
+        if let Some(td_qe_identity) = &self.td_qe_identity {
+            write("td_qe_identity.json", td_qe_identity)?;
+        }
+        if let Some(crl) = &self.root_ca_crl {
+            write(&crl_name("root_ca_crl", crl), crl)?;
+        }
+        for (i, pck_crl) in self.pck_crls.iter().enumerate() {
+            let stem = format!("pck_crl_{:02}", i);
+            write(&crl_name(&stem, &pck_crl.crl), &pck_crl.crl)?;
+            write(&format!("{}_issuer_chain.pem", stem), &pck_crl.issuer_chain)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|err| read_error(path, err))
+}
+
+#[cfg(feature = "std")]
+fn read_string(path: &Path) -> Result<String> {
+    String::from_utf8(read(path)?)
+        .map_err(|_| DcapError::Parse(format!("{} is not UTF-8", path.display())))
+}
+
+#[cfg(feature = "std")]
+fn read_optional(path: &Path) -> Result<Option<String>> {
+    path.exists().then(|| read_string(path)).transpose()
+}
+
+/// Keep PEM and hex CRLs as they are and hex encode DER ones, as in collateral bundles.
+#[cfg(feature = "std")]
+fn read_crl(path: &Path) -> Result<String> {
+    Ok(match String::from_utf8(read(path)?) {
+        Ok(text) => text,
+        Err(err) => hex::encode(err.into_bytes()),
+    })
+}
+
+#[cfg(feature = "std")]
+fn read_error(path: &Path, err: std::io::Error) -> DcapError {
+    DcapError::Parse(format!("Failed to read {}: {}", path.display(), err))
+}
+
+#[cfg(feature = "std")]
+fn write_error(path: &Path, err: std::io::Error) -> DcapError {
+    DcapError::Parse(format!("Failed to write {}: {}", path.display(), err))
+}
+
+fn decode_crl(raw: &str) -> Result<Vec<u8>> {
+    let raw = raw.trim();
+    if raw.starts_with("-----BEGIN") {
+        Ok(raw.as_bytes().to_vec())
+    } else {
+        hex::decode(raw).map_err(|err| DcapError::Parse(format!("Invalid CRL encoding: {}", err)))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{
+        auth_data::ECDSAQuoteV4AuthData,
+        policy::EnclavePolicy,
+        quote::Quote,
+        testing::{bundle, TestCa, TestPlatform},
+    };
+
+    #[test]
+    fn test_bundle_round_trip() -> Result<()> {
+        let root = TestCa::root("Test SGX Root CA").cert;
+        let bundle = CollateralBundle::load(bundle().to_json()?)?;
+        let collateral = bundle.collateral_with_root_ca(root)?;
+        assert_eq!(collateral.tcb_infos().count(), 2);
+        assert_eq!(collateral.crls().len(), 2);
+        assert!(collateral.td_qe_identity().is_ok());
+
+        for platform in [TestPlatform::new(), TestPlatform::tdx()] {
+            let body = platform.quote_body(4);
+            let signature = ECDSAQuoteV4AuthData::new(platform.sign(&body));
+            Quote { body, signature }.verify_at(
+                &collateral,
+                &EnclavePolicy::default(),
+                platform.verification_time(),
+            )?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_dir_round_trip() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("dcap-bundle-{}", std::process::id()));
+        let root = TestCa::root("Test SGX Root CA").cert;
+        let bundle = bundle();
+        bundle.save_dir(&dir)?;
+        let loaded = CollateralBundle::load_path(&dir, &intel_root_ca());
+
+        // DER CRLs are read as hex, and the root CA defaults to the one given.
+        let root_ca_crl = hex::decode(bundle.root_ca_crl.as_ref().unwrap()).unwrap();
+        fs::remove_file(dir.join("root_ca_crl.hex"))?;
+        fs::write(dir.join("root_ca_crl.der"), root_ca_crl)?;
+        fs::remove_file(dir.join("root_ca.pem"))?;
+        let defaulted = CollateralBundle::load_path(&dir, &root);
+
+        fs::remove_file(dir.join("tcb_signing_chain.pem"))?;
+        let incomplete = CollateralBundle::load_path(&dir, &root);
+        fs::remove_dir_all(&dir)?;
+
+        assert_eq!(loaded?.to_json()?, bundle.to_json()?);
+        let defaulted = defaulted?;
+        assert_eq!(defaulted.root_ca_crl, bundle.root_ca_crl);
+        assert_eq!(
+            parse_pem_chain(&defaulted.root_ca)?,
+            std::slice::from_ref(&root)
+        );
+        defaulted.collateral_with_root_ca(root)?;
+        assert!(matches!(incomplete, Err(DcapError::Parse(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_rejects_unknown_version() {
+        let mut bundle = bundle();
+        bundle.version = 2;
+        assert!(matches!(
+            CollateralBundle::load(bundle.to_json().unwrap()),
+            Err(DcapError::Unsupported(_))
+        ));
+        assert!(CollateralBundle::load(r#"{"version": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_bundle_pins_root_ca() {
+        let bundle = bundle();
+        // Anchored at Intel by default.
+        assert!(matches!(bundle.collateral(), Err(DcapError::Chain(_))));
+        let other = TestCa::root("Other Root CA").cert;
+        assert!(bundle.collateral_with_root_ca(other).is_err());
+
+        let mut tampered = bundle.clone();
+        tampered.tcb_infos[0] = tampered.tcb_infos[0].replace("00606a000000", "00606a000001");
+        let root = TestCa::root("Test SGX Root CA").cert;
+        assert!(matches!(
+            tampered.collateral_with_root_ca(root),
+            Err(DcapError::Signature(_))
+        ));
+    }
+}
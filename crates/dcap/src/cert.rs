@@ -1,130 +1,426 @@
-use std::{
-    collections::HashMap,
-    convert::{TryFrom, TryInto},
-};
+use alloc::{collections::BTreeMap, format, vec::Vec};
+use core::convert::{TryFrom, TryInto};
 
+use chrono::{DateTime, Utc};
 use der::{
-    asn1::{ObjectIdentifier, OctetString},
-    Decode,
+    asn1::{BitString, ObjectIdentifier, OctetString},
+    oid::AssociatedOid,
+    Decode, Encode, Tag, Tagged,
 };
+use p256::ecdsa::Signature;
 use x509_cert::{
     der::{self, Any, Sequence},
+    ext::pkix::{BasicConstraints, KeyUsage},
     Certificate,
 };
 
+use crate::{
+    error::{DcapError, Result},
+    signature::{verify_signature, VerifyingKey},
+};
+
 macro_rules! oid {
     ($h: expr) => {
         der::asn1::ObjectIdentifier::new_unwrap($h)
     };
 }
 
+/// DER encoding of the Intel SGX Root CA, the trust anchor of every PCK and TCB signing chain.
+/// refer: https://certificates.trustedservices.intel.com/Intel_SGX_Provisioning_Certification_RootCA.cer
+pub const INTEL_SGX_ROOT_CA_DER: &[u8] =
+    include_bytes!("../assets/Intel_SGX_Provisioning_Certification_RootCA.cer");
+
+/// The pinned Intel SGX Root CA.
+pub fn intel_root_ca() -> Certificate {
+    Certificate::from_der(INTEL_SGX_ROOT_CA_DER).expect("bundled Intel SGX Root CA is valid DER")
+}
+
+/// Check that `root` is a self-signed certificate.
+pub(crate) fn verify_self_signed(root: &Certificate) -> Result<()> {
+    if root.tbs_certificate.issuer != root.tbs_certificate.subject {
+        return Err(DcapError::Chain("Root CA is not self-issued".into()));
+    }
+    verify_cert_signature(root, root)
+}
+
+/// Parse a PEM encoded certificate chain, e.g. the `TCB-Info-Issuer-Chain` returned by the PCS.
+pub(crate) fn parse_pem_chain(raw: impl AsRef<[u8]>) -> Result<Vec<Certificate>> {
+    pem::parse_many(raw.as_ref())?
+        .iter()
+        .map(|pem| Ok(Certificate::from_der(pem.contents())?))
+        .collect()
+}
+
+/// Verify that `cert` is signed by the key of `issuer`.
+pub(crate) fn verify_cert_signature(issuer: &Certificate, cert: &Certificate) -> Result<()> {
+    verify_issuer_signature(issuer, &cert.signature, &cert.tbs_certificate)
+}
+
+/// Verify a DER encoded ECDSA `signature` made by the key of `issuer` over the DER encoding of
+/// `tbs`.
+pub(crate) fn verify_issuer_signature(
+    issuer: &Certificate,
+    signature: &BitString,
+    tbs: &impl Encode,
+) -> Result<()> {
+    let signature = Signature::from_der(signature.raw_bytes())
+        .map_err(|_| DcapError::Parse("Malformed certificate signature".into()))?;
+    verify_signature(
+        &VerifyingKey::from_spki(&issuer.tbs_certificate.subject_public_key_info)?,
+        signature.to_bytes(),
+        tbs.to_der()?,
+    )
+}
+
+/// Validate a certificate chain ordered from leaf to root at the point in time `now`.
+///
+/// Every certificate must be signed by the next one and be within its validity period, and
+/// every issuer must be a CA allowed to sign certificates at its depth in the chain. Whether
+/// the root itself is trusted is up to the caller.
+pub fn verify_chain(chain: &[Certificate], now: DateTime<Utc>) -> Result<()> {
+    verify_chain_structure(chain)?;
+    chain.iter().try_for_each(|cert| verify_validity(cert, now))
+}
+
+/// The time independent part of [`verify_chain`].
+pub(crate) fn verify_chain_structure(chain: &[Certificate]) -> Result<()> {
+    if chain.len() < 2 {
+        return Err(DcapError::Chain("Certificate chain is too short".into()));
+    }
+    chain
+        .windows(2)
+        .try_for_each(|pair| verify_cert_signature(&pair[1], &pair[0]))?;
+
+    // Issuers sit at depth 1.., and may only have `depth - 1` CAs below them.
+    chain
+        .iter()
+        .enumerate()
+        .skip(1)
+        .try_for_each(|(depth, cert)| verify_ca(cert, depth - 1))
+}
+
+/// Check that `cert` is valid at the point in time `now`.
+pub(crate) fn verify_validity(cert: &Certificate, now: DateTime<Utc>) -> Result<()> {
+    let validity = &cert.tbs_certificate.validity;
+    let now = now.timestamp();
+    if now < validity.not_before.to_unix_duration().as_secs() as i64 {
+        return Err(DcapError::Chain("Certificate is not yet valid".into()));
+    }
+    if now > validity.not_after.to_unix_duration().as_secs() as i64 {
+        return Err(DcapError::Chain("Certificate has expired".into()));
+    }
+    Ok(())
+}
+
+/// Check that `cert` may issue certificates with `sub_cas` intermediate CAs below it.
+fn verify_ca(cert: &Certificate, sub_cas: usize) -> Result<()> {
+    let basic_constraints = find_extension::<BasicConstraints>(cert)?
+        .ok_or_else(|| DcapError::Chain("Issuer is missing basic constraints".into()))?;
+    if !basic_constraints.ca {
+        return Err(DcapError::Chain("Issuer is not a CA".into()));
+    }
+    if let Some(path_len) = basic_constraints.path_len_constraint {
+        if sub_cas > path_len as usize {
+            return Err(DcapError::Chain(
+                "Issuer path length constraint exceeded".into(),
+            ));
+        }
+    }
+    if let Some(key_usage) = find_extension::<KeyUsage>(cert)? {
+        if !key_usage.key_cert_sign() {
+            return Err(DcapError::Chain(
+                "Issuer is not allowed to sign certificates".into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn find_extension<'a, E>(cert: &'a Certificate) -> Result<Option<E>>
+where
+    E: AssociatedOid + Decode<'a>,
+{
+    cert.tbs_certificate
+        .extensions
+        .iter()
+        .flatten()
+        .find(|ext| ext.extn_id == E::OID)
+        .map(|ext| E::from_der(ext.extn_value.as_bytes()))
+        .transpose()
+        .map_err(Into::into)
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Sequence, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tcb {
     pub pce_svn: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub comp_svn_array: [u8; 16],
 }
 
-#[allow(clippy::upper_case_acronyms)]
-pub struct PCK {
-    inner: Certificate,
-    pub fmspc: [u8; 6],
-    pub pce_id: [u8; 2],
+/// The SGX Type of the platform a PCK certificate was issued to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SgxType {
+    Standard,
+    Scalable,
+    ScalableWithIntegrity,
+}
+
+/// Configuration of a multi-package platform, each flag is optional.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlatformConfiguration {
+    pub dynamic_platform: Option<bool>,
+    pub cached_keys: Option<bool>,
+    pub smt_enabled: Option<bool>,
+}
+
+/// The SGX extensions (OID 1.2.840.113741.1.13.1) of a PCK certificate.
+/// refer: https://api.trustedservices.intel.com/documents/Intel_SGX_PCK_Certificate_CRL_Spec-1.5.pdf
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SgxExtensions {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ppid: [u8; 16],
     pub tcb: Tcb,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub cpu_svn: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub pce_id: [u8; 2],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub fmspc: [u8; 6],
+    pub sgx_type: SgxType,
+    /// Only present in certificates issued by the PCK Platform CA.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option"))]
+    pub platform_instance_id: Option<[u8; 16]>,
+    /// Only present in certificates issued by the PCK Platform CA.
+    pub configuration: Option<PlatformConfiguration>,
 }
 
-impl std::ops::Deref for PCK {
-    type Target = Certificate;
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
+pub(crate) const SGX_EXTENSIONS_OID: ObjectIdentifier = oid!("1.2.840.113741.1.13.1");
+
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct SgxExtension {
+    key: ObjectIdentifier,
+    value: Any,
 }
 
-impl PCK {
-    pub fn new(cert: Certificate) -> Self {
-        const SGX_EXTENSION_OID: ObjectIdentifier = oid!("1.2.840.113741.1.13.1");
-
-        const TCB_OID: ObjectIdentifier = oid!("1.2.840.113741.1.13.1.2");
-        const TCB_COMPSVN_OID: [ObjectIdentifier; 16] = [
-            oid!("1.2.840.113741.1.13.1.2.1"),
-            oid!("1.2.840.113741.1.13.1.2.2"),
-            oid!("1.2.840.113741.1.13.1.2.3"),
-            oid!("1.2.840.113741.1.13.1.2.4"),
-            oid!("1.2.840.113741.1.13.1.2.5"),
-            oid!("1.2.840.113741.1.13.1.2.6"),
-            oid!("1.2.840.113741.1.13.1.2.7"),
-            oid!("1.2.840.113741.1.13.1.2.8"),
-            oid!("1.2.840.113741.1.13.1.2.9"),
-            oid!("1.2.840.113741.1.13.1.2.10"),
-            oid!("1.2.840.113741.1.13.1.2.11"),
-            oid!("1.2.840.113741.1.13.1.2.12"),
-            oid!("1.2.840.113741.1.13.1.2.13"),
-            oid!("1.2.840.113741.1.13.1.2.14"),
-            oid!("1.2.840.113741.1.13.1.2.15"),
-            oid!("1.2.840.113741.1.13.1.2.16"),
-        ];
-        const TCB_PCESVN_OID: ObjectIdentifier = oid!("1.2.840.113741.1.13.1.2.17");
-
-        const PCEID_OID: ObjectIdentifier = oid!("1.2.840.113741.1.13.1.3");
-        const FMSPC_OID: ObjectIdentifier = oid!("1.2.840.113741.1.13.1.4");
-
-        #[derive(Clone, Debug, Eq, PartialEq, Sequence)]
-        struct Ext {
-            pub key: ObjectIdentifier,
-            pub value: Any,
-        }
+/// The values of a sequence of SGX extensions keyed by the last arc of their OID.
+struct SgxExtensionMap(BTreeMap<ObjectIdentifier, Any>);
 
+impl SgxExtensionMap {
+    fn from_cert(cert: &Certificate) -> Result<Self> {
         let raw_ext = cert
             .tbs_certificate
             .extensions
             .as_ref()
-            .and_then(|exts| {
-                exts.iter()
-                    .find(|ext| ext.extn_id == SGX_EXTENSION_OID)
-                    .cloned()
-            })
-            .unwrap();
+            .and_then(|exts| exts.iter().find(|ext| ext.extn_id == SGX_EXTENSIONS_OID))
+            .ok_or_else(|| DcapError::Parse("PCK certificate has no SGX extensions".into()))?;
+        Self::decode(&Any::from_der(raw_ext.extn_value.as_bytes())?)
+    }
 
-        let sgx_exts = Vec::<Ext>::from_der(raw_ext.extn_value.as_bytes())
-            .unwrap()
-            .into_iter()
-            .map(|ext| (ext.key, ext.value))
-            .collect::<HashMap<ObjectIdentifier, Any>>();
+    fn decode(any: &Any) -> Result<Self> {
+        Ok(Self(
+            any.decode_as::<Vec<SgxExtension>>()?
+                .into_iter()
+                .map(|ext| (ext.key, ext.value))
+                .collect(),
+        ))
+    }
 
-        let fmspc = OctetString::try_from(&sgx_exts[&FMSPC_OID])
-            .unwrap()
-            .as_bytes()
-            .try_into()
-            .unwrap();
-        let pce_id = OctetString::try_from(&sgx_exts[&PCEID_OID])
-            .unwrap()
+    fn oid(arcs: &str) -> Result<ObjectIdentifier> {
+        ObjectIdentifier::new(&format!("{}.{}", SGX_EXTENSIONS_OID, arcs))
+            .map_err(|_| DcapError::Parse(format!("Invalid SGX extension {}", arcs)))
+    }
+
+    fn find(&self, arcs: &str) -> Result<Option<&Any>> {
+        Ok(self.0.get(&Self::oid(arcs)?))
+    }
+
+    fn get(&self, arcs: &str) -> Result<&Any> {
+        self.find(arcs)?.ok_or_else(|| {
+            DcapError::Parse(format!(
+                "SGX extension {}.{} doesn't exist",
+                SGX_EXTENSIONS_OID, arcs
+            ))
+        })
+    }
+
+    fn octets<const N: usize>(&self, arcs: &str) -> Result<[u8; N]> {
+        Ok(OctetString::try_from(self.get(arcs)?)?
             .as_bytes()
-            .try_into()
-            .unwrap();
-
-        let tcb = sgx_exts[&TCB_OID].decode_as::<Vec<Ext>>().unwrap();
-        let tcb = tcb
-            .into_iter()
-            .map(|ext| (ext.key, ext.value))
-            .collect::<HashMap<_, _>>();
-
-        let pce_svn = tcb[&TCB_PCESVN_OID].decode_as::<u16>().unwrap();
-
-        let comp_svn_array = TCB_COMPSVN_OID
-            .iter()
-            .map(|oid| tcb[oid].decode_as::<u8>().unwrap())
-            .collect::<Vec<_>>()
-            .as_slice()
-            .try_into()
-            .unwrap();
-
-        Self {
-            inner: cert,
-            fmspc,
-            pce_id,
+            .try_into()?)
+    }
+}
+
+impl SgxExtensions {
+    /// Parse the SGX extensions of a PCK certificate.
+    pub fn from_cert(cert: &Certificate) -> Result<Self> {
+        let exts = SgxExtensionMap::from_cert(cert)?;
+
+        let tcb = SgxExtensionMap::decode(exts.get("2")?)?;
+        let mut comp_svn_array = [0; 16];
+        for (i, svn) in comp_svn_array.iter_mut().enumerate() {
+            *svn = tcb.get(&format!("2.{}", i + 1))?.decode_as::<u8>()?;
+        }
+        let pce_svn = tcb.get("2.17")?.decode_as::<u16>()?;
+
+        let sgx_type = exts.get("5")?;
+        let sgx_type = match (sgx_type.tag(), sgx_type.value()) {
+            (Tag::Enumerated, [0]) => SgxType::Standard,
+            (Tag::Enumerated, [1]) => SgxType::Scalable,
+            (Tag::Enumerated, [2]) => SgxType::ScalableWithIntegrity,
+            _ => return Err(DcapError::Parse("Invalid SGX Type".into())),
+        };
+
+        let platform_instance_id = match exts.find("6")? {
+            Some(id) => Some(OctetString::try_from(id)?.as_bytes().try_into()?),
+            None => None,
+        };
+        let configuration = exts
+            .find("7")?
+            .map(|configuration| -> Result<_> {
+                let configuration = SgxExtensionMap::decode(configuration)?;
+                let flag = |arcs| -> Result<Option<bool>> {
+                    configuration
+                        .find(arcs)?
+                        .map(|flag| flag.decode_as::<bool>())
+                        .transpose()
+                        .map_err(Into::into)
+                };
+                Ok(PlatformConfiguration {
+                    dynamic_platform: flag("7.1")?,
+                    cached_keys: flag("7.2")?,
+                    smt_enabled: flag("7.3")?,
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            ppid: exts.octets("1")?,
             tcb: Tcb {
                 pce_svn,
                 comp_svn_array,
             },
-        }
+            cpu_svn: tcb.octets("2.18")?,
+            pce_id: exts.octets("3")?,
+            fmspc: exts.octets("4")?,
+            sgx_type,
+            platform_instance_id,
+            configuration,
+        })
+    }
+}
+
+/// The DER encoded `SEQUENCE { OID, value }` of the top-level SGX extension `arcs` of `cert`,
+/// as it appears in the `TBSCertificate`.
+pub(crate) fn sgx_extension_der(cert: &Certificate, arcs: &str) -> Result<Vec<u8>> {
+    let extension = SgxExtension {
+        key: SgxExtensionMap::oid(arcs)?,
+        value: SgxExtensionMap::from_cert(cert)?.get(arcs)?.clone(),
+    };
+    Ok(extension.to_der()?)
+}
+
+#[allow(clippy::upper_case_acronyms)]
+pub struct PCK {
+    inner: Certificate,
+    pub extensions: SgxExtensions,
+}
+
+impl core::ops::Deref for PCK {
+    type Target = Certificate;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl PCK {
+    pub fn into_inner(self) -> Certificate {
+        self.inner
+    }
+
+    pub fn new(cert: Certificate) -> Result<Self> {
+        Ok(Self {
+            extensions: SgxExtensions::from_cert(&cert)?,
+            inner: cert,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::testing::TestCa;
+
+    fn at(raw: &str) -> DateTime<Utc> {
+        raw.parse().unwrap()
+    }
+
+    #[test]
+    fn test_intel_root_ca_is_self_signed() -> Result<()> {
+        verify_self_signed(&intel_root_ca())
+    }
+
+    #[test]
+    fn test_sgx_extensions_multi_package() -> Result<()> {
+        let extensions = SgxExtensions {
+            ppid: [0x11; 16],
+            tcb: Tcb {
+                pce_svn: 13,
+                comp_svn_array: [12, 12, 3, 3, 255, 255, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            },
+            cpu_svn: [12, 12, 3, 3, 255, 255, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            pce_id: [0, 0],
+            fmspc: [0x00, 0x90, 0x6e, 0xd5, 0x00, 0x00],
+            sgx_type: SgxType::Scalable,
+            platform_instance_id: Some([0x22; 16]),
+            configuration: Some(PlatformConfiguration {
+                dynamic_platform: Some(true),
+                cached_keys: Some(false),
+                smt_enabled: None,
+            }),
+        };
+        let pck = TestCa::root("Test Root CA")
+            .issue_ca("Test Platform CA")
+            .issue_pck("Test PCK Certificate", &extensions);
+        assert_eq!(PCK::new(pck.cert)?.extensions, extensions);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_chain_validity_period() -> Result<()> {
+        let root = TestCa::root("Test Root CA");
+        let ca = root.issue_ca("Test Platform CA");
+        let leaf = ca.issue_leaf("Test PCK Certificate");
+        let chain = [leaf.cert, ca.cert, root.cert];
+
+        verify_chain(&chain, at("2024-01-01T00:00:00Z"))?;
+        assert!(verify_chain(&chain, at("2017-12-31T23:59:59Z")).is_err());
+        assert!(verify_chain(&chain, at("2050-01-01T00:00:00Z")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_chain_rejects_non_ca_issuer() {
+        let root = TestCa::root("Test Root CA");
+        let leaf = root.issue_leaf("Test Leaf");
+        let forged = leaf.issue_leaf("Test Forged Leaf");
+        let chain = [forged.cert, leaf.cert, root.cert];
+
+        assert!(verify_chain(&chain, at("2024-01-01T00:00:00Z")).is_err());
+    }
+
+    #[test]
+    fn test_verify_chain_rejects_path_length_violation() {
+        let root = TestCa::root("Test Root CA");
+        // The intermediate is constrained to `pathLen = 0` and cannot issue another CA.
+        let ca = root.issue_ca("Test Platform CA");
+        let sub_ca = ca.issue_ca("Test Sub CA");
+        let leaf = sub_ca.issue_leaf("Test Leaf");
+        let chain = [leaf.cert, sub_ca.cert, ca.cert, root.cert];
+
+        assert!(verify_chain(&chain, at("2024-01-01T00:00:00Z")).is_err());
     }
 }
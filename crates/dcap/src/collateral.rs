@@ -0,0 +1,507 @@
+use alloc::{boxed::Box, collections::BTreeMap, format, string::String, vec, vec::Vec};
+
+use chrono::{DateTime, Utc};
+use serde_json::value::RawValue;
+use x509_cert::{crl::CertificateList, Certificate};
+
+use super::{
+    cert::{
+        intel_root_ca, parse_pem_chain, verify_chain_structure, verify_self_signed, verify_validity,
+    },
+    crl::{parse_crl, verify_crl, verify_crl_validity, verify_not_listed, verify_not_revoked},
+    enclave::{EnclaveId, EnclaveIdentityId},
+    error::{DcapError, Result},
+    signature::{verify_signature, VerifyingKey},
+    tcb_info::{TcbInfo, TcbInfoId},
+};
+
+/// Verification collateral fetched from the Intel PCS.
+///
+/// Holds one SGX and one TDX TCB Info per FMSPC and PCE ID together with the QE and TD QE
+/// Identities, so a single verifier can serve many platforms and collateral can be rotated at
+/// runtime without a rebuild.
+/// Every document is checked against the TCB signing key before it is accepted, and the TCB
+/// signing chain as well as every PCK chain must be anchored at the same root CA.
+/// Every CA of those chains must have published a CRL, and certificates listed on one of the
+/// CRLs are rejected.
+pub struct Collateral {
+    root_ca: Certificate,
+    tcb_signing_chain: Vec<Certificate>,
+    crls: Vec<CertificateList>,
+    allow_missing_crls: bool,
+    tcb_infos: BTreeMap<(TcbInfoId, [u8; 6], [u8; 2]), TcbInfo>,
+    qe_identity: EnclaveId,
+    td_qe_identity: Option<EnclaveId>,
+}
+
+impl Collateral {
+    /// Build collateral from the raw JSON documents returned by the PCS `tcb` and
+    /// `qe/identity` endpoints, and the PEM encoded `TCB-Info-Issuer-Chain` that signed them.
+    ///
+    /// The issuer chain must end in the pinned Intel SGX Root CA.
+    pub fn new<T: AsRef<[u8]>>(
+        tcb_infos: &[T],
+        qe_identity: impl AsRef<[u8]>,
+        tcb_signing_chain: impl AsRef<[u8]>,
+    ) -> Result<Self> {
+        Self::with_root_ca(intel_root_ca(), tcb_infos, qe_identity, tcb_signing_chain)
+    }
+
+    /// Same as [`Collateral::new`] but anchored at `root_ca` instead of the Intel SGX Root CA,
+    /// e.g. for test infrastructure with its own root.
+    pub fn with_root_ca<T: AsRef<[u8]>>(
+        root_ca: Certificate,
+        tcb_infos: &[T],
+        qe_identity: impl AsRef<[u8]>,
+        tcb_signing_chain: impl AsRef<[u8]>,
+    ) -> Result<Self> {
+        verify_self_signed(&root_ca)?;
+
+        let tcb_signing_chain = parse_pem_chain(tcb_signing_chain)?;
+        verify_chain_structure(&tcb_signing_chain)?;
+        if tcb_signing_chain.last() != Some(&root_ca) {
+            return Err(DcapError::Chain(
+                "TCB signing chain is not anchored at the trusted root CA".into(),
+            ));
+        }
+
+        let mut collateral = Self {
+            root_ca,
+            tcb_signing_chain,
+            crls: vec![],
+            allow_missing_crls: false,
+            tcb_infos: BTreeMap::new(),
+            qe_identity: EnclaveId::default(),
+            td_qe_identity: None,
+        };
+        collateral.set_qe_identity(qe_identity)?;
+        for tcb_info in tcb_infos {
+            collateral.add_tcb_info(tcb_info)?;
+        }
+        Ok(collateral)
+    }
+
+    /// Add an SGX or TDX TCB Info document, replacing any previous one of the same kind for the
+    /// same FMSPC and PCE ID.
+    pub fn add_tcb_info(&mut self, raw: impl AsRef<[u8]>) -> Result<()> {
+        verify_signed_json(raw.as_ref(), "tcbInfo", &self.tcb_signing_key()?)?;
+        let tcb_info = TcbInfo::load(raw)?;
+        self.tcb_infos
+            .insert((tcb_info.id, tcb_info.fmspc, tcb_info.pce_id), tcb_info);
+        Ok(())
+    }
+
+    /// Replace the QE Identity document.
+    pub fn set_qe_identity(&mut self, raw: impl AsRef<[u8]>) -> Result<()> {
+        self.qe_identity = self.load_enclave_identity(raw, EnclaveIdentityId::Qe)?;
+        Ok(())
+    }
+
+    /// Replace the TD QE Identity document, required to verify TDX quotes.
+    pub fn set_td_qe_identity(&mut self, raw: impl AsRef<[u8]>) -> Result<()> {
+        self.td_qe_identity = Some(self.load_enclave_identity(raw, EnclaveIdentityId::TdQe)?);
+        Ok(())
+    }
+
+    fn load_enclave_identity(
+        &self,
+        raw: impl AsRef<[u8]>,
+        id: EnclaveIdentityId,
+    ) -> Result<EnclaveId> {
+        verify_signed_json(raw.as_ref(), "enclaveIdentity", &self.tcb_signing_key()?)?;
+        let enclave_id = EnclaveId::load(raw)?;
+        match enclave_id.id {
+            Some(actual) if actual != id => Err(DcapError::CollateralMismatch(format!(
+                "Expected {} identity, got {}",
+                id, actual
+            ))),
+            _ => Ok(enclave_id),
+        }
+    }
+
+    /// Add the CRL published by the root CA (`rootcacrl`), DER or PEM encoded.
+    pub fn add_root_ca_crl(&mut self, raw: impl AsRef<[u8]>) -> Result<()> {
+        let crl = parse_crl(raw)?;
+        verify_crl(&self.root_ca, &crl)?;
+        self.crls.push(crl);
+        Ok(())
+    }
+
+    /// Add a CRL published by the PCK Platform or Processor CA (`pckcrl`), DER or PEM encoded,
+    /// together with the PEM encoded `SGX-PCK-CRL-Issuer-Chain` returned alongside it.
+    pub fn add_pck_crl(
+        &mut self,
+        raw: impl AsRef<[u8]>,
+        issuer_chain: impl AsRef<[u8]>,
+    ) -> Result<()> {
+        let issuer_chain = parse_pem_chain(issuer_chain)?;
+        verify_chain_structure(&issuer_chain)?;
+        if issuer_chain.last() != Some(&self.root_ca) {
+            return Err(DcapError::Chain(
+                "CRL issuer chain is not anchored at the trusted root CA".into(),
+            ));
+        }
+        self.verify_chain_not_revoked(&issuer_chain)?;
+
+        let crl = parse_crl(raw)?;
+        verify_crl(&issuer_chain[0], &crl)?;
+        self.crls.push(crl);
+        Ok(())
+    }
+
+    pub fn crls(&self) -> &[CertificateList] {
+        &self.crls
+    }
+
+    /// Accept chains whose CAs have not published a CRL in this collateral, e.g. when CRLs are
+    /// not fetched. Certificates listed on the CRLs that are present are still rejected.
+    pub fn allow_missing_crls(&mut self) {
+        self.allow_missing_crls = true;
+    }
+
+    /// Check that neither `chain` nor the TCB signing chain has been revoked.
+    pub(crate) fn verify_not_revoked(&self, chain: &[Certificate]) -> Result<()> {
+        self.verify_chain_not_revoked(chain)?;
+        self.verify_chain_not_revoked(&self.tcb_signing_chain)
+    }
+
+    fn verify_chain_not_revoked(&self, chain: &[Certificate]) -> Result<()> {
+        if self.allow_missing_crls {
+            verify_not_listed(chain, &self.crls)
+        } else {
+            verify_not_revoked(chain, &self.crls)
+        }
+    }
+
+    /// Check that the TCB signing chain and every CRL are valid at `now`.
+    ///
+    /// TCB Infos and identities are checked on use, so collateral may still hold stale ones
+    /// for other platforms.
+    pub fn verify_validity(&self, now: DateTime<Utc>) -> Result<()> {
+        self.tcb_signing_chain
+            .iter()
+            .try_for_each(|cert| verify_validity(cert, now))?;
+        self.crls
+            .iter()
+            .try_for_each(|crl| verify_crl_validity(crl, now))
+    }
+
+    /// The root CA every certificate chain is anchored at.
+    pub fn root_ca(&self) -> &Certificate {
+        &self.root_ca
+    }
+
+    /// The TCB signing certificate chain, ordered from the signing certificate to the root.
+    pub fn tcb_signing_chain(&self) -> &[Certificate] {
+        &self.tcb_signing_chain
+    }
+
+    fn tcb_signing_key(&self) -> Result<VerifyingKey> {
+        let signer = self
+            .tcb_signing_chain
+            .first()
+            .ok_or_else(|| DcapError::Chain("Missing TCB signing certificate".into()))?;
+        VerifyingKey::from_spki(&signer.tbs_certificate.subject_public_key_info)
+    }
+
+    pub fn tcb_info(&self, fmspc: &[u8; 6], pce_id: &[u8; 2]) -> Result<&TcbInfo> {
+        self.find_tcb_info(TcbInfoId::Sgx, fmspc, pce_id)
+    }
+
+    pub fn tdx_tcb_info(&self, fmspc: &[u8; 6], pce_id: &[u8; 2]) -> Result<&TcbInfo> {
+        self.find_tcb_info(TcbInfoId::Tdx, fmspc, pce_id)
+    }
+
+    fn find_tcb_info(&self, id: TcbInfoId, fmspc: &[u8; 6], pce_id: &[u8; 2]) -> Result<&TcbInfo> {
+        self.tcb_infos.get(&(id, *fmspc, *pce_id)).ok_or_else(|| {
+            DcapError::CollateralMismatch(format!(
+                "No {:?} TCB Info for FMSPC {} and PCE ID {}",
+                id,
+                hex::encode(fmspc),
+                hex::encode(pce_id)
+            ))
+        })
+    }
+
+    pub fn tcb_infos(&self) -> impl Iterator<Item = &TcbInfo> {
+        self.tcb_infos.values()
+    }
+
+    pub fn qe_identity(&self) -> &EnclaveId {
+        &self.qe_identity
+    }
+
+    pub fn td_qe_identity(&self) -> Result<&EnclaveId> {
+        self.td_qe_identity
+            .as_ref()
+            .ok_or_else(|| DcapError::CollateralMismatch("TD QE identity doesn't exist".into()))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+impl Collateral {
+    /// Collateral anchored at the Intel SGX Root CA whose documents are checked against
+    /// `tcb_signing_key` alone.
+    ///
+    /// The Intel TCB signing certificate is not vendored, so this is the only way to exercise
+    /// genuine quotes against genuine PCS documents. The TCB signing chain is left empty.
+    pub(crate) fn with_tcb_signing_key<T: AsRef<[u8]>>(
+        tcb_signing_key: &VerifyingKey,
+        tcb_infos: &[T],
+        qe_identity: impl AsRef<[u8]>,
+    ) -> Result<Self> {
+        let mut tcb_infos_map = BTreeMap::new();
+        for tcb_info in tcb_infos {
+            verify_signed_json(tcb_info.as_ref(), "tcbInfo", tcb_signing_key)?;
+            let tcb_info = TcbInfo::load(tcb_info)?;
+            tcb_infos_map.insert((tcb_info.id, tcb_info.fmspc, tcb_info.pce_id), tcb_info);
+        }
+        verify_signed_json(qe_identity.as_ref(), "enclaveIdentity", tcb_signing_key)?;
+        Ok(Self {
+            root_ca: intel_root_ca(),
+            tcb_signing_chain: vec![],
+            crls: vec![],
+            allow_missing_crls: false,
+            tcb_infos: tcb_infos_map,
+            qe_identity: EnclaveId::load(qe_identity)?,
+            td_qe_identity: None,
+        })
+    }
+}
+
+/// Check the `signature` of a signed PCS document against the exact bytes of its `body_key`
+/// member, as Intel signs the body as it appears on the wire.
+fn verify_signed_json(raw: &[u8], body_key: &str, key: &VerifyingKey) -> Result<()> {
+    let envelope: BTreeMap<String, Box<RawValue>> = serde_json::from_slice(raw)?;
+    let body = envelope
+        .get(body_key)
+        .ok_or_else(|| DcapError::Parse(format!("{} doesn't exist", body_key)))?;
+    let signature = envelope
+        .get("signature")
+        .and_then(|v| serde_json::from_str::<String>(v.get()).ok())
+        .and_then(|v| hex::decode(v).ok())
+        .ok_or_else(|| DcapError::Parse("signature doesn't exist or cannot be parsed".into()))?;
+
+    verify_signature(key, signature, body.get())
+        .map_err(|_| DcapError::Signature(format!("Invalid signature for {}", body_key)))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use der::Encode;
+    use hex_literal::hex;
+
+    use super::*;
+    use crate::testing::{self, TestCa};
+
+    #[test]
+    fn test_collateral_lookup_by_fmspc_and_pce_id() -> Result<()> {
+        let collateral = testing::collateral();
+        let pce_id = hex!("0000");
+        assert!(collateral.tcb_info(&hex!("00606a000000"), &pce_id).is_ok());
+        assert!(collateral.tcb_info(&hex!("00906ed50000"), &pce_id).is_err());
+        assert!(collateral
+            .tcb_info(&hex!("00606a000000"), &hex!("0001"))
+            .is_err());
+        assert_eq!(collateral.qe_identity().isvprodid, 1);
+
+        // SGX and TDX TCB Info are kept apart even for the same FMSPC.
+        assert!(collateral
+            .tdx_tcb_info(&hex!("00806f050000"), &pce_id)
+            .is_ok());
+        assert!(collateral.tcb_info(&hex!("00806f050000"), &pce_id).is_err());
+        assert!(collateral
+            .tdx_tcb_info(&hex!("00606a000000"), &pce_id)
+            .is_err());
+        assert_eq!(collateral.td_qe_identity()?.isvprodid, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_collateral_checks_identity_id() {
+        let signer = TestCa::tcb_signer();
+        let qe_identity = signer.sign_json(include_str!("../assets/identity.json"));
+        let td_qe_identity = signer.sign_json(include_str!("../assets/td_identity.json"));
+
+        let mut collateral = testing::collateral();
+        assert!(collateral.set_td_qe_identity(&qe_identity).is_err());
+        assert!(collateral.set_qe_identity(&td_qe_identity).is_err());
+        assert!(collateral.set_td_qe_identity(&td_qe_identity).is_ok());
+    }
+
+    #[test]
+    fn test_verify_intel_signed_json() -> Result<()> {
+        let key = VerifyingKey::from_sec1_bytes(testing::INTEL_TCB_SIGNING_KEY)?;
+        let tcb_info = include_bytes!("../assets/pcs_tcbinfo.json");
+        let qe_identity = include_bytes!("../assets/pcs_identity.json");
+        verify_signed_json(tcb_info, "tcbInfo", &key)?;
+        verify_signed_json(qe_identity, "enclaveIdentity", &key)?;
+
+        // The body is signed as served, so the pretty-printed copies no longer verify.
+        let pretty = include_bytes!("../assets/tcbinfo.json");
+        assert!(verify_signed_json(pretty, "tcbInfo", &key).is_err());
+        assert!(verify_signed_json(tcb_info, "enclaveIdentity", &key).is_err());
+        let other = TestCa::tcb_signer()
+            .cert
+            .tbs_certificate
+            .subject_public_key_info;
+        let other = VerifyingKey::from_spki(&other)?;
+        assert!(verify_signed_json(qe_identity, "enclaveIdentity", &other).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_collateral_rejects_bad_signature() {
+        let signer = TestCa::tcb_signer();
+        let tcb_info = signer.sign_json(include_str!("../assets/tcbinfo.json"));
+        let qe_identity = signer.sign_json(include_str!("../assets/identity.json"));
+
+        let root = signer.issuers[0].clone();
+        let load = |tcb_info: &str, qe_identity: &str| {
+            Collateral::with_root_ca(root.clone(), &[tcb_info], qe_identity, signer.chain_pem())
+        };
+
+        // The assets as shipped carry Intel's signature, which this signer didn't make.
+        assert!(load(include_str!("../assets/tcbinfo.json"), &qe_identity).is_err());
+        assert!(load(&tcb_info, include_str!("../assets/identity.json")).is_err());
+
+        // Tampering with the body invalidates the signature.
+        let tampered = tcb_info.replace("\"pceId\": \"0000\"", "\"pceId\": \"0001\"");
+        assert_ne!(tampered, tcb_info);
+        assert!(load(&tampered, &qe_identity).is_err());
+
+        // A chain whose signing certificate was not issued by its root is rejected.
+        let other = TestCa::root("Other Root CA");
+        let broken_chain = testing::chain_pem(&[&signer.cert, &other.cert]);
+        assert!(Collateral::with_root_ca(
+            other.cert.clone(),
+            &[&tcb_info],
+            &qe_identity,
+            broken_chain
+        )
+        .is_err());
+
+        assert!(load(&tcb_info, &qe_identity).is_ok());
+    }
+
+    #[test]
+    fn test_collateral_crls() -> Result<()> {
+        let signer = TestCa::tcb_signer();
+        let root = TestCa::root("Test SGX Root CA");
+        let ca = root.issue_ca("Test SGX PCK Platform CA");
+        let pck = ca.issue_leaf("Test SGX PCK Certificate");
+        let chain = [pck.cert.clone(), ca.cert.clone(), root.cert.clone()];
+        let ca_chain = testing::chain_pem(&[&ca.cert, &root.cert]);
+
+        let mut collateral = testing::collateral();
+        collateral.add_root_ca_crl(root.crl(&[]).to_der()?)?;
+        collateral.add_pck_crl(ca.crl(&[]).to_der()?, &ca_chain)?;
+        collateral.verify_not_revoked(&chain)?;
+
+        // Revocation fails closed when a CA of either chain has not published a CRL.
+        let new = || {
+            Collateral::with_root_ca(
+                root.cert.clone(),
+                &[signer.sign_json(include_str!("../assets/tcbinfo.json"))],
+                signer.sign_json(include_str!("../assets/identity.json")),
+                signer.chain_pem(),
+            )
+        };
+        let mut missing = new()?;
+        assert!(matches!(
+            missing.verify_not_revoked(&chain),
+            Err(DcapError::CollateralMismatch(_))
+        ));
+        assert!(missing
+            .add_pck_crl(ca.crl(&[]).to_der()?, &ca_chain)
+            .is_err());
+        missing.add_root_ca_crl(root.crl(&[]).to_der()?)?;
+        assert!(matches!(
+            missing.verify_not_revoked(&chain),
+            Err(DcapError::CollateralMismatch(_))
+        ));
+        missing.add_pck_crl(ca.crl(&[]).to_der()?, &ca_chain)?;
+        missing.verify_not_revoked(&chain)?;
+
+        // Unless explicitly allowed, which still honors the CRLs that are present.
+        let mut allowed = new()?;
+        allowed.allow_missing_crls();
+        allowed.verify_not_revoked(&chain)?;
+        allowed.add_pck_crl(ca.crl(&[&pck.cert]).to_der()?, &ca_chain)?;
+        assert!(allowed.verify_not_revoked(&chain).is_err());
+
+        // CRLs must be signed by the CA they claim to come from.
+        let other = TestCa::root("Other Root CA");
+        assert!(collateral
+            .add_root_ca_crl(other.crl(&[]).to_der()?)
+            .is_err());
+        assert!(collateral
+            .add_pck_crl(other.crl(&[]).to_der()?, &ca_chain)
+            .is_err());
+
+        let mut revoked_pck = testing::collateral();
+        revoked_pck.add_pck_crl(ca.crl(&[&pck.cert]).to_der()?, &ca_chain)?;
+        assert!(revoked_pck.verify_not_revoked(&chain).is_err());
+
+        let mut revoked_ca = testing::collateral();
+        revoked_ca.add_root_ca_crl(root.crl(&[&ca.cert]).to_der()?)?;
+        assert!(revoked_ca.verify_not_revoked(&chain).is_err());
+        // A revoked PCK CA cannot publish CRLs either.
+        assert!(revoked_ca
+            .add_pck_crl(ca.crl(&[]).to_der()?, &ca_chain)
+            .is_err());
+
+        // Revoking the TCB signing certificate invalidates every chain.
+        let mut revoked_signer = testing::collateral();
+        revoked_signer.add_root_ca_crl(root.crl(&[&signer.cert]).to_der()?)?;
+        assert!(revoked_signer.verify_not_revoked(&chain).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_collateral_validity() -> Result<()> {
+        let collateral = testing::collateral();
+        collateral.verify_validity(testing::verification_time())?;
+
+        // The test certificates and CRLs are valid from 2018 until the end of 2049.
+        let at = |raw: &str| raw.parse::<DateTime<Utc>>().unwrap();
+        assert!(matches!(
+            collateral.verify_validity(at("2017-12-31T00:00:00Z")),
+            Err(DcapError::Chain(_))
+        ));
+        assert!(matches!(
+            collateral.verify_validity(at("2050-01-01T00:00:00Z")),
+            Err(DcapError::Chain(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_collateral_pins_root_ca() {
+        let signer = TestCa::tcb_signer();
+        let tcb_info = signer.sign_json(include_str!("../assets/tcbinfo.json"));
+        let qe_identity = signer.sign_json(include_str!("../assets/identity.json"));
+
+        // A well-formed chain under a foreign root is not trusted by default.
+        assert!(Collateral::new(&[&tcb_info], &qe_identity, signer.chain_pem()).is_err());
+
+        // Nor under a root which is not the one it was told to trust.
+        let other = TestCa::root("Other Root CA");
+        assert!(Collateral::with_root_ca(
+            other.cert,
+            &[&tcb_info],
+            &qe_identity,
+            signer.chain_pem()
+        )
+        .is_err());
+
+        // An overriding root must be self-signed.
+        assert!(Collateral::with_root_ca(
+            signer.cert.clone(),
+            &[&tcb_info],
+            &qe_identity,
+            signer.chain_pem()
+        )
+        .is_err());
+    }
+}
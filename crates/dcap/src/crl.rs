@@ -0,0 +1,159 @@
+use super::error::{DcapError, Result};
+use alloc::format;
+use chrono::{DateTime, Utc};
+use der::Decode;
+use x509_cert::{crl::CertificateList, ext::pkix::KeyUsage, Certificate};
+
+use super::cert::{find_extension, verify_issuer_signature};
+
+/// Parse a CRL as served by the PCS, either DER encoded or wrapped in a PEM `X509 CRL` block.
+pub(crate) fn parse_crl(raw: impl AsRef<[u8]>) -> Result<CertificateList> {
+    let raw = raw.as_ref();
+    if raw.starts_with(b"-----BEGIN") {
+        let pem = pem::parse(raw)?;
+        Ok(CertificateList::from_der(pem.contents())?)
+    } else {
+        Ok(CertificateList::from_der(raw)?)
+    }
+}
+
+/// Check that `crl` was issued and signed by `issuer`.
+pub(crate) fn verify_crl(issuer: &Certificate, crl: &CertificateList) -> Result<()> {
+    if crl.tbs_cert_list.issuer != issuer.tbs_certificate.subject {
+        return Err(DcapError::CollateralMismatch(
+            "CRL issuer does not match the issuer certificate".into(),
+        ));
+    }
+    if let Some(key_usage) = find_extension::<KeyUsage>(issuer)? {
+        if !key_usage.crl_sign() {
+            return Err(DcapError::Chain(
+                "Issuer is not allowed to sign CRLs".into(),
+            ));
+        }
+    }
+    verify_issuer_signature(issuer, &crl.signature, &crl.tbs_cert_list)
+        .map_err(|_| DcapError::Signature("Invalid CRL signature".into()))
+}
+
+/// Check that `crl` has been issued and not passed its next update at `now`.
+pub(crate) fn verify_crl_validity(crl: &CertificateList, now: DateTime<Utc>) -> Result<()> {
+    let now = now.timestamp();
+    let this_update = crl.tbs_cert_list.this_update.to_unix_duration().as_secs() as i64;
+    if now < this_update {
+        return Err(DcapError::CollateralExpired("CRL is not yet issued".into()));
+    }
+    if let Some(next_update) = crl.tbs_cert_list.next_update {
+        if now > next_update.to_unix_duration().as_secs() as i64 {
+            return Err(DcapError::CollateralExpired(
+                "CRL has passed its next update".into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check that every certificate of `chain` but the self-signed root is covered by a CRL
+/// published by its issuer, and that none is listed on one.
+pub(crate) fn verify_not_revoked(chain: &[Certificate], crls: &[CertificateList]) -> Result<()> {
+    for cert in chain {
+        let issuer = &cert.tbs_certificate.issuer;
+        if issuer != &cert.tbs_certificate.subject
+            && !crls.iter().any(|crl| &crl.tbs_cert_list.issuer == issuer)
+        {
+            return Err(DcapError::CollateralMismatch(format!(
+                "No CRL published by {}",
+                issuer
+            )));
+        }
+    }
+    verify_not_listed(chain, crls)
+}
+
+/// Check that no certificate of `chain` is listed on a CRL published by its issuer.
+pub(crate) fn verify_not_listed(chain: &[Certificate], crls: &[CertificateList]) -> Result<()> {
+    for cert in chain {
+        let revoked = crls
+            .iter()
+            .filter(|crl| crl.tbs_cert_list.issuer == cert.tbs_certificate.issuer)
+            .flat_map(|crl| crl.tbs_cert_list.revoked_certificates.iter().flatten())
+            .any(|entry| entry.serial_number == cert.tbs_certificate.serial_number);
+        if revoked {
+            return Err(DcapError::Chain(format!(
+                "Certificate {} is revoked",
+                cert.tbs_certificate.subject
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use der::Encode;
+
+    use super::*;
+    use crate::testing::TestCa;
+
+    #[test]
+    fn test_parse_crl_der_and_pem() -> Result<()> {
+        let root = TestCa::root("Test Root CA");
+        let crl = root.crl(&[]);
+        assert_eq!(parse_crl(crl.to_der()?)?, crl);
+        let pem = pem::encode(&pem::Pem::new("X509 CRL", crl.to_der()?));
+        assert_eq!(parse_crl(pem)?, crl);
+        assert!(parse_crl(b"garbage").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_crl_signature() {
+        let root = TestCa::root("Test Root CA");
+        let other = TestCa::root("Other Root CA");
+        let crl = root.crl(&[]);
+        assert!(verify_crl(&root.cert, &crl).is_ok());
+        assert!(verify_crl(&other.cert, &crl).is_err());
+
+        let mut tampered = crl.clone();
+        tampered.tbs_cert_list.issuer = other.cert.tbs_certificate.subject.clone();
+        assert!(verify_crl(&other.cert, &tampered).is_err());
+    }
+
+    #[test]
+    fn test_verify_crl_validity() {
+        let crl = TestCa::root("Test Root CA").crl(&[]);
+        let at = |raw: &str| raw.parse::<DateTime<Utc>>().unwrap();
+        assert!(verify_crl_validity(&crl, at("2024-01-01T00:00:00Z")).is_ok());
+        assert!(verify_crl_validity(&crl, at("2017-12-31T00:00:00Z")).is_err());
+        assert!(verify_crl_validity(&crl, at("2050-01-01T00:00:00Z")).is_err());
+    }
+
+    #[test]
+    fn test_verify_not_revoked() {
+        let root = TestCa::root("Test Root CA");
+        let ca = root.issue_ca("Test Platform CA");
+        let pck = ca.issue_leaf("Test PCK Certificate");
+        let other = ca.issue_leaf("Other PCK Certificate");
+        let chain = [pck.cert.clone(), ca.cert.clone(), root.cert.clone()];
+
+        let root_crl = root.crl(&[]);
+        let ca_crl = ca.crl(&[&other.cert]);
+        assert!(verify_not_revoked(&chain, &[root_crl.clone(), ca_crl.clone()]).is_ok());
+        assert!(verify_not_revoked(&chain, &[root_crl.clone(), ca.crl(&[&pck.cert])]).is_err());
+        assert!(verify_not_revoked(&chain, &[root.crl(&[&ca.cert]), ca_crl.clone()]).is_err());
+        // A CRL only revokes certificates from its own issuer.
+        assert!(verify_not_revoked(&chain, &[root.crl(&[&pck.cert]), ca_crl.clone()]).is_ok());
+
+        // Every issuer below the root must have published a CRL.
+        let missing = |crls: &[CertificateList]| {
+            matches!(
+                verify_not_revoked(&chain, crls),
+                Err(DcapError::CollateralMismatch(_))
+            )
+        };
+        assert!(missing(&[]));
+        assert!(missing(&[root_crl]));
+        assert!(missing(&[ca.crl(&[])]));
+        assert!(verify_not_listed(&chain, &[ca_crl]).is_ok());
+        assert!(verify_not_listed(&chain, &[ca.crl(&[&pck.cert])]).is_err());
+    }
+}
@@ -1,112 +1,306 @@
-use std::{convert::TryInto};
+use alloc::{format, string::String, vec::Vec};
+use core::{cmp::Reverse, str::FromStr};
 
-use anyhow::{anyhow, Result};
-use serde_json::Value;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
 
-#[derive(PartialEq, Eq)]
+use super::{
+    error::{DcapError, Result},
+    serde_hex,
+};
+
+/// The enclave an identity document describes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EnclaveIdentityId {
+    /// The SGX Quoting Enclave, `qe/identity`.
+    #[cfg_attr(feature = "serde", serde(rename = "QE"))]
+    Qe,
+    /// The Quote Verification Enclave, `qve/identity`.
+    #[cfg_attr(feature = "serde", serde(rename = "QVE"))]
+    Qve,
+    /// The TDX Quoting Enclave, `tdx/.../qe/identity`.
+    #[cfg_attr(feature = "serde", serde(rename = "TD_QE"))]
+    TdQe,
+}
+
+impl EnclaveIdentityId {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Qe => "QE",
+            Self::Qve => "QVE",
+            Self::TdQe => "TD_QE",
+        }
+    }
+}
+
+impl FromStr for EnclaveIdentityId {
+    type Err = DcapError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "QE" => Self::Qe,
+            "QVE" => Self::Qve,
+            "TD_QE" => Self::TdQe,
+            _ => {
+                return Err(DcapError::Parse(format!(
+                    "Unknown enclave identity id {}",
+                    s
+                )))
+            }
+        })
+    }
+}
+
+impl core::fmt::Display for EnclaveIdentityId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Status of an enclave identity TCB level as defined by the Intel PCS.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EnclaveIdStatus {
-    OK,
-    #[allow(non_camel_case_types)]
-    SGX_ENCLAVE_REPORT_ISVSVN_REVOKED,
+    UpToDate,
+    OutOfDate,
+    Revoked,
+}
+
+impl EnclaveIdStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UpToDate => "UpToDate",
+            Self::OutOfDate => "OutOfDate",
+            Self::Revoked => "Revoked",
+        }
+    }
 }
 
+impl FromStr for EnclaveIdStatus {
+    type Err = DcapError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "UpToDate" => Self::UpToDate,
+            "OutOfDate" => Self::OutOfDate,
+            "Revoked" => Self::Revoked,
+            _ => {
+                return Err(DcapError::Parse(format!(
+                    "Unknown enclave tcb status {}",
+                    s
+                )))
+            }
+        })
+    }
+}
+
+impl core::fmt::Display for EnclaveIdStatus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TcbLevel {
     pub isvsvn: u16,
+    pub tcb_date: DateTime<Utc>,
     pub tcb_status: EnclaveIdStatus,
+    pub advisory_ids: Vec<String>,
 }
 
+/// An enclave identity as served by the Intel PCS for the QE, QVE and TD QE.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EnclaveId {
+    /// Absent from v1 documents.
+    pub id: Option<EnclaveIdentityId>,
+    pub version: u8,
+    pub issue_date: DateTime<Utc>,
+    pub next_update: DateTime<Utc>,
+    pub tcb_evaluation_data_number: u8,
     pub miscselect: u32,
     pub miscselect_mask: u32,
     pub isvprodid: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub attributes: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub attributes_mask: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub mrsigner: [u8; 32],
+    /// Sorted from the highest ISVSVN down.
     pub tcb_levels: Vec<TcbLevel>,
 }
 
 impl EnclaveId {
-    pub fn get() -> &'static Self {
-        use std::sync::OnceLock;
-        static READ_ONLY: OnceLock<EnclaveId> = OnceLock::new();
-        READ_ONLY.get_or_init(|| Self::load().unwrap())
-    }
+    /// Parse an enclave identity document as served by the Intel PCS `qe/identity`,
+    /// `qve/identity` and `tdx/certification/v4/qe/identity` endpoints.
+    pub fn load(raw: impl AsRef<[u8]>) -> Result<Self> {
+        let EnclaveIdDocument {
+            enclave_identity: json,
+        } = serde_json::from_slice(raw.as_ref())?;
 
-    fn load() -> Result<Self> {
-        let raw = include_str!("../assets/identity.json");
-        let value: Value = serde_json::from_str(raw)?;
-        let value = value
-            .get("enclaveIdentity")
-            .ok_or(anyhow!("Invalid format"))?;
-
-        macro_rules! load_hex {
-            ($name: expr) => {
-                value
-                    .get($name)
-                    .and_then(|v| v.as_str())
-                    .and_then(|v| hex::decode(v.as_bytes()).ok())
-                    .and_then(|v| TryInto::try_into(v.as_slice()).ok())
-                    .ok_or(anyhow!("{} doesn't exist or cannot be parsed", $name))
-            };
-        }
-
-        let miscselect = load_hex!("miscselect").and_then(|v| Ok(u32::from_le_bytes(v)))?;
-        let miscselect_mask =
-            load_hex!("miscselectMask").and_then(|v| Ok(u32::from_le_bytes(v)))?;
-        let attributes = load_hex!("attributes")?;
-        let attributes_mask = load_hex!("attributesMask")?;
-        let isvprodid = value
-            .get("isvprodid")
-            .and_then(|v| v.as_u64())
-            .map(|v| v as u16)
-            .ok_or(anyhow!("isvprodid doesn't exist or cannot be parsed",))?;
-
-        let mrsigner = load_hex!("mrsigner").and_then(|bytes: Vec<u8>| {
-            if bytes.len() != 32 {
-                Err(anyhow!(
-                    "Invalid mrsigner bytes length. MrSigner should be 32-bytes",
-                ))
-            } else {
-                Ok(bytes.as_slice().try_into().unwrap())
-            }
-        })?;
-
-        let tcb_levels = value
-            .get("tcbLevels")
-            .and_then(|v| v.as_array())
-            .and_then(|tcb_levels| {
-                tcb_levels
-                    .iter()
-                    .map(|tcb_level| {
-                        Some(TcbLevel {
-                            isvsvn: tcb_level
-                                .get("tcb")
-                                .and_then(|v| v.get("isvsvn"))
-                                .and_then(|v| v.as_u64())
-                                .map(|v| v as u16)?,
-                            tcb_status: tcb_level.get("tcbStatus").and_then(|v| v.as_str()).map(
-                                |v| {
-                                    if v == "UpToDate" {
-                                        EnclaveIdStatus::OK
-                                    } else {
-                                        EnclaveIdStatus::SGX_ENCLAVE_REPORT_ISVSVN_REVOKED
-                                    }
-                                },
-                            )?,
-                        })
-                    })
-                    .collect::<Option<Vec<_>>>()
+        let mut tcb_levels = json
+            .tcb_levels
+            .into_iter()
+            .map(|level| {
+                Ok(TcbLevel {
+                    isvsvn: level.tcb.isvsvn,
+                    tcb_date: level.tcb_date,
+                    tcb_status: level.tcb_status.parse()?,
+                    advisory_ids: level.advisory_ids,
+                })
             })
-            .ok_or(anyhow!("tcbLevels don't exist or cannot be parsed",))?;
+            .collect::<Result<Vec<_>>>()?;
+        tcb_levels.sort_by_key(|level| Reverse(level.isvsvn));
 
         Ok(Self {
-            miscselect,
-            miscselect_mask,
-            isvprodid,
-            attributes,
-            attributes_mask,
-            mrsigner,
+            id: json.id.as_deref().map(str::parse).transpose()?,
+            version: json.version,
+            issue_date: json.issue_date,
+            next_update: json.next_update,
+            tcb_evaluation_data_number: json.tcb_evaluation_data_number,
+            miscselect: u32::from_le_bytes(json.miscselect),
+            miscselect_mask: u32::from_le_bytes(json.miscselect_mask),
+            isvprodid: json.isvprodid,
+            attributes: json.attributes,
+            attributes_mask: json.attributes_mask,
+            mrsigner: json.mrsigner,
             tcb_levels,
         })
     }
+
+    /// The highest TCB level not above `isvsvn`, if any.
+    pub fn tcb_level(&self, isvsvn: u16) -> Option<&TcbLevel> {
+        self.tcb_levels.iter().find(|level| level.isvsvn <= isvsvn)
+    }
+
+    /// Check that this identity has been issued and not passed its next update at `now`.
+    pub fn verify_validity(&self, now: DateTime<Utc>) -> Result<()> {
+        if now < self.issue_date {
+            return Err(DcapError::CollateralExpired(
+                "Enclave identity is not yet issued".into(),
+            ));
+        }
+        if now > self.next_update {
+            return Err(DcapError::CollateralExpired(
+                "Enclave identity has passed its next update".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// An enclave identity document in the format of the PCS identity endpoints.
+#[derive(Deserialize)]
+struct EnclaveIdDocument {
+    #[serde(rename = "enclaveIdentity")]
+    enclave_identity: EnclaveIdJson,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnclaveIdJson {
+    id: Option<String>,
+    version: u8,
+    issue_date: DateTime<Utc>,
+    next_update: DateTime<Utc>,
+    tcb_evaluation_data_number: u8,
+    #[serde(deserialize_with = "serde_hex::deserialize")]
+    miscselect: [u8; 4],
+    #[serde(deserialize_with = "serde_hex::deserialize")]
+    miscselect_mask: [u8; 4],
+    isvprodid: u16,
+    #[serde(deserialize_with = "serde_hex::deserialize")]
+    attributes: [u8; 16],
+    #[serde(deserialize_with = "serde_hex::deserialize")]
+    attributes_mask: [u8; 16],
+    #[serde(deserialize_with = "serde_hex::deserialize")]
+    mrsigner: [u8; 32],
+    tcb_levels: Vec<EnclaveTcbLevelJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnclaveTcbLevelJson {
+    tcb: EnclaveTcbJson,
+    tcb_date: DateTime<Utc>,
+    tcb_status: String,
+    #[serde(rename = "advisoryIDs", default)]
+    advisory_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct EnclaveTcbJson {
+    isvsvn: u16,
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_enclave_identity_json() -> Result<()> {
+        let identity = EnclaveId::load(include_str!("../assets/identity.json"))?;
+        assert_eq!(identity.id, Some(EnclaveIdentityId::Qe));
+        assert_eq!(identity.version, 2);
+        assert_eq!(identity.tcb_evaluation_data_number, 16);
+        assert_eq!(identity.isvprodid, 1);
+        assert_eq!(identity.miscselect_mask, 0xffffffff);
+        assert_eq!(identity.tcb_levels[0].tcb_status, EnclaveIdStatus::UpToDate);
+        assert_eq!(
+            identity.tcb_levels[0].tcb_date,
+            "2023-08-09T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        assert_eq!(identity.tcb_level(8).unwrap().isvsvn, 8);
+        let level = identity.tcb_level(7).unwrap();
+        assert_eq!(level.isvsvn, 6);
+        assert_eq!(level.tcb_status, EnclaveIdStatus::OutOfDate);
+        assert!(identity.tcb_level(0).is_none());
+
+        let td_identity = EnclaveId::load(include_str!("../assets/td_identity.json"))?;
+        assert_eq!(td_identity.id, Some(EnclaveIdentityId::TdQe));
+        Ok(())
+    }
+
+    #[test]
+    fn test_enclave_identity_sorts_levels() -> Result<()> {
+        let mut raw: serde_json::Value =
+            serde_json::from_str(include_str!("../assets/identity.json"))?;
+        let levels = raw["enclaveIdentity"]["tcbLevels"].as_array_mut().unwrap();
+        levels.reverse();
+        levels.swap(0, 2);
+        let identity = EnclaveId::load(raw.to_string())?;
+
+        let isvsvns = identity.tcb_levels.iter().map(|level| level.isvsvn);
+        assert!(isvsvns.clone().zip(isvsvns.skip(1)).all(|(a, b)| a > b));
+        assert_eq!(identity.tcb_level(8).unwrap().isvsvn, 8);
+        assert_eq!(identity.tcb_level(7).unwrap().isvsvn, 6);
+        assert_eq!(identity.tcb_level(100).unwrap().isvsvn, 8);
+        assert!(identity.tcb_level(0).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_enclave_identity_levels() -> Result<()> {
+        let raw = include_str!("../assets/identity.json")
+            .replace(r#""id": "QE""#, r#""id": "QVE""#)
+            .replace(
+                r#""tcbStatus": "OutOfDate""#,
+                r#""tcbStatus": "Revoked", "advisoryIDs": ["INTEL-SA-00615"]"#,
+            );
+        let identity = EnclaveId::load(raw)?;
+        assert_eq!(identity.id, Some(EnclaveIdentityId::Qve));
+        let level = identity.tcb_level(6).unwrap();
+        assert_eq!(level.tcb_status, EnclaveIdStatus::Revoked);
+        assert_eq!(level.advisory_ids, ["INTEL-SA-00615"]);
+
+        let raw = include_str!("../assets/identity.json").replace("OutOfDate", "Unknown");
+        assert!(EnclaveId::load(raw).is_err());
+        let raw = include_str!("../assets/identity.json").replace(r#""QE""#, r#""PCE""#);
+        assert!(EnclaveId::load(raw).is_err());
+        Ok(())
+    }
 }
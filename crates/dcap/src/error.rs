@@ -0,0 +1,156 @@
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use super::tcb_info::TcbStatus;
+
+/// Why a quote or its collateral was rejected.
+///
+/// Variants group failures by their cause, so callers can map them to responses and metrics
+/// without matching on messages. The messages are meant for logs only.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DcapError {
+    /// Malformed quote, certificate, CRL or collateral document.
+    Parse(String),
+    /// Well-formed input this crate does not support, e.g. an unknown quote version.
+    Unsupported(String),
+    /// A certificate chain is not anchored at the trusted root, violates CA constraints, is
+    /// outside its validity period or revoked.
+    Chain(String),
+    /// A signature over a quote, QE report, certificate, CRL or collateral document is invalid.
+    Signature(String),
+    /// The collateral does not cover the quote, e.g. no TCB Info for its FMSPC or a QE that
+    /// does not match the QE identity.
+    CollateralMismatch(String),
+    /// A TCB Info, identity or CRL is not yet issued or past its next update at the time of
+    /// verification.
+    CollateralExpired(String),
+    /// No TCB level of the TCB Info matches the platform, or no TCB level of the enclave
+    /// identity matches the QE.
+    TcbNotFound,
+    /// The platform is at a TCB level that cannot be trusted.
+    TcbStatus(TcbStatus),
+    /// The enclave or TD is not allowed by the enclave policy.
+    Policy(String),
+}
+
+pub type Result<T> = core::result::Result<T, DcapError>;
+
+/// The steps of quote verification, numbered like the `STEPn` comments of the verifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationStep {
+    Header = 1,
+    Policy,
+    QeIdentity,
+    PckChainParse,
+    PckCollateral,
+    Tcb,
+    PckChain,
+    QeReportSignature,
+    QuoteSignature,
+}
+
+impl VerificationStep {
+    pub fn number(self) -> u8 {
+        self as u8
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Header => "parse and verify header",
+            Self::Policy => "verify the enclave or TD against the policy",
+            Self::QeIdentity => "verify the QE identity",
+            Self::PckChainParse => "parse the PCK certificate chain",
+            Self::PckCollateral => "match the PCK against the TCB Info",
+            Self::Tcb => "find the TCB level",
+            Self::PckChain => "verify the PCK certificate chain",
+            Self::QeReportSignature => "verify the QE report signature",
+            Self::QuoteSignature => "verify the attestation signature",
+        }
+    }
+}
+
+impl fmt::Display for VerificationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "STEP{} ({})", self.number(), self.description())
+    }
+}
+
+/// A [`DcapError`] together with the verification step it was raised in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepError {
+    pub step: VerificationStep,
+    pub error: DcapError,
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.step, self.error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StepError {}
+
+pub type StepResult<T> = core::result::Result<T, StepError>;
+
+impl From<StepError> for DcapError {
+    fn from(err: StepError) -> Self {
+        err.error
+    }
+}
+
+/// Attach the verification step to an error.
+pub(crate) trait AtStep<T> {
+    fn at(self, step: VerificationStep) -> StepResult<T>;
+}
+
+impl<T> AtStep<T> for Result<T> {
+    fn at(self, step: VerificationStep) -> StepResult<T> {
+        self.map_err(|error| StepError { step, error })
+    }
+}
+
+impl fmt::Display for DcapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(msg) => write!(f, "Parse error: {}", msg),
+            Self::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            Self::Chain(msg) => write!(f, "Invalid certificate chain: {}", msg),
+            Self::Signature(msg) => write!(f, "Invalid signature: {}", msg),
+            Self::CollateralMismatch(msg) => write!(f, "Collateral mismatch: {}", msg),
+            Self::CollateralExpired(msg) => write!(f, "Collateral expired: {}", msg),
+            Self::TcbNotFound => {
+                f.write_str("TCB not found: no TCB level matches the platform or QE")
+            }
+            Self::TcbStatus(status) => write!(f, "TCB status {} is not trusted", status),
+            Self::Policy(msg) => write!(f, "Rejected by policy: {}", msg),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DcapError {}
+
+impl From<der::Error> for DcapError {
+    fn from(err: der::Error) -> Self {
+        Self::Parse(err.to_string())
+    }
+}
+
+impl From<pem::PemError> for DcapError {
+    fn from(err: pem::PemError) -> Self {
+        Self::Parse(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for DcapError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Parse(err.to_string())
+    }
+}
+
+impl From<core::array::TryFromSliceError> for DcapError {
+    fn from(err: core::array::TryFromSliceError) -> Self {
+        Self::Parse(err.to_string())
+    }
+}
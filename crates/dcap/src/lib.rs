@@ -1,11 +1,43 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod abi;
 mod auth_data;
+mod bundle;
 mod cert;
+mod collateral;
+mod crl;
 mod enclave;
+mod error;
+mod policy;
 mod quote;
+mod quote_ref;
+mod report;
+mod serde_hex;
 mod tcb_info;
 mod traits;
 
 pub mod signature;
+// Like the tests using it, it needs `std`.
+#[cfg(any(all(test, feature = "std"), feature = "testing"))]
+pub mod testing;
+pub use abi::{QuoteCalldata, TbsOffsets};
 pub use auth_data::*;
+pub use bundle::{CollateralBundle, PckCrl};
+pub use cert::{
+    intel_root_ca, verify_chain, PlatformConfiguration, SgxExtensions, SgxType, Tcb,
+    INTEL_SGX_ROOT_CA_DER, PCK,
+};
+pub use collateral::*;
+pub use enclave::{EnclaveId, EnclaveIdStatus, EnclaveIdentityId, TcbLevel};
+pub use error::{DcapError, Result, StepError, StepResult, VerificationStep};
+pub use policy::EnclavePolicy;
 pub use quote::*;
-pub use traits::{BinRepr, Verifiable};
+pub use quote_ref::QuoteRef;
+pub use report::*;
+pub use tcb_info::{
+    TcbComponent, TcbInfo, TcbInfoId, TcbLevelInfo, TcbLevelSvns, TcbStatus, TdxModule,
+    TdxModuleIdentity, TdxModuleTcbLevel,
+};
+pub use traits::{BinRepr, StepVerifiable, Verifiable};
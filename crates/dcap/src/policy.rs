@@ -0,0 +1,196 @@
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+
+use super::error::{DcapError, Result};
+
+use super::quote::{EnclaveReport, RawEnclaveId, RawSigner, TdReport10};
+
+/// The enclaves a verifier accepts quotes from.
+///
+/// Empty MRENCLAVE or MRSIGNER sets accept any measurement. Once a minimum ISVSVN is set for
+/// some ISVPRODID, enclaves with any other ISVPRODID are rejected. Debug enclaves are rejected
+/// unless explicitly allowed.
+#[derive(Clone, Debug)]
+pub struct EnclavePolicy {
+    mr_enclaves: Vec<RawEnclaveId>,
+    mr_signers: Vec<RawSigner>,
+    min_isv_svn: BTreeMap<u16, u16>,
+    required_attributes: [u8; 16],
+    forbidden_attributes: [u8; 16],
+}
+
+impl Default for EnclavePolicy {
+    fn default() -> Self {
+        Self {
+            mr_enclaves: vec![],
+            mr_signers: vec![],
+            min_isv_svn: BTreeMap::new(),
+            required_attributes: [0; 16],
+            forbidden_attributes: Self::ATTRIBUTE_DEBUG,
+        }
+    }
+}
+
+impl EnclavePolicy {
+    /// The DEBUG bit of the SGX enclave attributes.
+    pub const ATTRIBUTE_DEBUG: [u8; 16] = [0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    /// The DEBUG bit of the TD attributes.
+    const TD_ATTRIBUTE_DEBUG: u8 = 0x01;
+
+    pub fn trust_enclave(mut self, mr_enclave: RawEnclaveId) -> Self {
+        self.mr_enclaves.push(mr_enclave);
+        self
+    }
+
+    pub fn trust_signer(mut self, mr_signer: RawSigner) -> Self {
+        self.mr_signers.push(mr_signer);
+        self
+    }
+
+    /// Accept enclaves with `isv_prod_id` from `isv_svn` on.
+    pub fn min_isv_svn(mut self, isv_prod_id: u16, isv_svn: u16) -> Self {
+        self.min_isv_svn.insert(isv_prod_id, isv_svn);
+        self
+    }
+
+    /// Require every bit set in `attributes`.
+    pub fn require_attributes(mut self, attributes: [u8; 16]) -> Self {
+        self.required_attributes = attributes;
+        self
+    }
+
+    /// Reject enclaves with any bit set in `attributes`, DEBUG by default.
+    pub fn forbid_attributes(mut self, attributes: [u8; 16]) -> Self {
+        self.forbidden_attributes = attributes;
+        self
+    }
+
+    /// Accept debug enclaves and TDs, whose memory is open to the host.
+    pub fn allow_debug(mut self) -> Self {
+        for (forbidden, debug) in self
+            .forbidden_attributes
+            .iter_mut()
+            .zip(Self::ATTRIBUTE_DEBUG)
+        {
+            *forbidden &= !debug;
+        }
+        self
+    }
+
+    pub fn verify_enclave(&self, report: &EnclaveReport) -> Result<()> {
+        if !self.mr_enclaves.is_empty() && !self.mr_enclaves.contains(&report.mr_enclave) {
+            return Err(DcapError::Policy("Enclave not trusted".into()));
+        }
+        if !self.mr_signers.is_empty() && !self.mr_signers.contains(&report.mr_signer) {
+            return Err(DcapError::Policy("Signer not trusted".into()));
+        }
+        if !self.min_isv_svn.is_empty() {
+            match self.min_isv_svn.get(&report.isv_prod_id) {
+                Some(min_isv_svn) if report.isv_svn >= *min_isv_svn => {}
+                Some(_) => return Err(DcapError::Policy("Enclave ISVSVN is too low".into())),
+                None => return Err(DcapError::Policy("Enclave ISVPRODID not trusted".into())),
+            }
+        }
+
+        let attributes = report.attributes.iter();
+        let required = self.required_attributes.iter();
+        let forbidden = self.forbidden_attributes.iter();
+        if !attributes
+            .zip(required.zip(forbidden))
+            .all(|(a, (r, f))| a & r == *r && a & f == 0)
+        {
+            return Err(DcapError::Policy("Enclave attributes not allowed".into()));
+        }
+        Ok(())
+    }
+
+    /// The policy only describes SGX enclaves, so TD reports are rejected as soon as it
+    /// restricts measurements.
+    pub fn verify_td(&self, report: &TdReport10) -> Result<()> {
+        if !self.mr_enclaves.is_empty()
+            || !self.mr_signers.is_empty()
+            || !self.min_isv_svn.is_empty()
+        {
+            return Err(DcapError::Policy(
+                "Enclave policy does not apply to TD reports".into(),
+            ));
+        }
+        let debug_forbidden = self.forbidden_attributes[0] & Self::ATTRIBUTE_DEBUG[0] != 0;
+        if debug_forbidden && report.td_attributes[0] & Self::TD_ATTRIBUTE_DEBUG != 0 {
+            return Err(DcapError::Policy("TD attributes not allowed".into()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::traits::BinRepr;
+
+    fn enclave_report() -> EnclaveReport {
+        let mut report = EnclaveReport::from_bytes(&[0; 384]).unwrap();
+        report.attributes[0] = 0x05;
+        report.mr_enclave = [0x11; 32];
+        report.mr_signer = [0x22; 32];
+        report.isv_prod_id = 1;
+        report.isv_svn = 3;
+        report
+    }
+
+    #[test]
+    fn test_enclave_policy_measurements() {
+        let report = enclave_report();
+        assert!(EnclavePolicy::default().verify_enclave(&report).is_ok());
+
+        let policy = EnclavePolicy::default()
+            .trust_enclave([0x33; 32])
+            .trust_enclave([0x11; 32])
+            .trust_signer([0x22; 32]);
+        assert!(policy.verify_enclave(&report).is_ok());
+        let policy = EnclavePolicy::default().trust_enclave([0x33; 32]);
+        assert!(policy.verify_enclave(&report).is_err());
+        let policy = EnclavePolicy::default().trust_signer([0x33; 32]);
+        assert!(policy.verify_enclave(&report).is_err());
+    }
+
+    #[test]
+    fn test_enclave_policy_isv_svn() {
+        let report = enclave_report();
+        let policy = EnclavePolicy::default().min_isv_svn(1, 3).min_isv_svn(2, 0);
+        assert!(policy.verify_enclave(&report).is_ok());
+        let policy = EnclavePolicy::default().min_isv_svn(1, 4);
+        assert!(policy.verify_enclave(&report).is_err());
+        let policy = EnclavePolicy::default().min_isv_svn(2, 0);
+        assert!(policy.verify_enclave(&report).is_err());
+    }
+
+    #[test]
+    fn test_enclave_policy_attributes() {
+        let mut report = enclave_report();
+        let mode64bit = [0x04, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let policy = EnclavePolicy::default().require_attributes(mode64bit);
+        assert!(policy.verify_enclave(&report).is_ok());
+        let policy = EnclavePolicy::default().forbid_attributes(mode64bit);
+        assert!(policy.verify_enclave(&report).is_err());
+
+        report.attributes[0] |= 0x02;
+        assert!(EnclavePolicy::default().verify_enclave(&report).is_err());
+        let policy = EnclavePolicy::default().allow_debug();
+        assert!(policy.verify_enclave(&report).is_ok());
+    }
+
+    #[test]
+    fn test_enclave_policy_td() {
+        let mut report = TdReport10::from_bytes(&[0; 584]).unwrap();
+        assert!(EnclavePolicy::default().verify_td(&report).is_ok());
+        let policy = EnclavePolicy::default().trust_signer([0x22; 32]);
+        assert!(policy.verify_td(&report).is_err());
+
+        report.td_attributes[0] = 0x01;
+        assert!(EnclavePolicy::default().verify_td(&report).is_err());
+        assert!(EnclavePolicy::default()
+            .allow_debug()
+            .verify_td(&report)
+            .is_ok());
+    }
+}
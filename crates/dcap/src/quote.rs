@@ -1,8 +1,33 @@
-use std::{convert::TryInto, mem::MaybeUninit};
+use alloc::{format, vec::Vec};
+use core::{convert::TryInto, mem::size_of};
 
-use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 
-use super::traits::{BinRepr, Verifiable};
+use super::{
+    collateral::Collateral,
+    error::{AtStep, DcapError, Result, StepResult, VerificationStep},
+    policy::EnclavePolicy,
+    traits::{BinRepr, StepVerifiable, Verifiable},
+};
+
+/// Reads the fields of a fixed size structure in order, from a buffer of that size.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn array<const N: usize>(&mut self) -> [u8; N] {
+        let (field, rest) = self.0.split_at(N);
+        self.0 = rest;
+        field.try_into().expect("field of N bytes")
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.array())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.array())
+    }
+}
 
 /// SGX Quote Header Struct
 /// refer: https://download.01.org/intel-sgx/dcap-1.1/linux/docs/Intel_SGX_ECDSA_QuoteGenReference_DCAP_API_Linux_1.1.pdf
@@ -10,70 +35,143 @@ use super::traits::{BinRepr, Verifiable};
 /// sgx_quote_header_t: 48bytes
 ///     version: 2bytes
 ///     attestation_key_type: 2bytes
-///     reserved1: 4bytes
+///     tee_type: 4bytes
 ///     qe_svn: 2bytes
 ///     pce_svn: 2bytes
 ///     qe_vendor_id: 16bytes
 ///     user_data: 20bytes
 ///
-/// Force it use C layout to avoid data rerrangement.
+/// All integers are little-endian.
 #[repr(C)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     pub version: u16,
     pub attestation_key_type: u16,
     pub tee_type: u32,
     pub qe_svn: u16,
     pub pce_svn: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub qe_vendor_id: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub user_data: [u8; 20],
 }
 
+// The fields add up to the encoded size, without padding.
+const _: () = assert!(size_of::<Header>() == 48);
+
 impl BinRepr for Header {
     fn from_bytes(bytes: &[u8]) -> Result<Self> {
         if bytes.len() != 48 {
-            return Err(anyhow!("Invalid length for Quote Header"));
+            return Err(DcapError::Parse("Invalid length for Quote Header".into()));
         }
 
-        Ok(unsafe {
-            let mut uninit = MaybeUninit::<Header>::uninit();
-
-            std::ptr::copy_nonoverlapping(
-                bytes.as_ptr(),
-                uninit.as_mut_ptr() as *mut u8,
-                bytes.len(),
-            );
-
-            uninit.assume_init()
+        let mut reader = Reader(bytes);
+        Ok(Self {
+            version: reader.u16(),
+            attestation_key_type: reader.u16(),
+            tee_type: reader.u32(),
+            qe_svn: reader.u16(),
+            pce_svn: reader.u16(),
+            qe_vendor_id: reader.array(),
+            user_data: reader.array(),
         })
     }
 
     fn to_bytes(&self) -> Result<Vec<u8>> {
-        unsafe {
-            let bytes: [u8; 48] = *(self as *const Header as *const [u8; 48]);
-            Ok(bytes.to_vec())
-        }
+        let mut bytes = Vec::with_capacity(48);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&self.attestation_key_type.to_le_bytes());
+        bytes.extend_from_slice(&self.tee_type.to_le_bytes());
+        bytes.extend_from_slice(&self.qe_svn.to_le_bytes());
+        bytes.extend_from_slice(&self.pce_svn.to_le_bytes());
+        bytes.extend_from_slice(&self.qe_vendor_id);
+        bytes.extend_from_slice(&self.user_data);
+        Ok(bytes)
+    }
+}
+
+impl Header {
+    pub const TEE_TYPE_SGX: u32 = 0x00;
+    pub const TEE_TYPE_TDX: u32 = 0x81;
+}
+
+/// A [`Header`] borrowed from the encoding of a quote, see [`QuoteRef`].
+///
+/// [`QuoteRef`]: crate::QuoteRef
+#[derive(Clone, Copy, Debug)]
+pub struct HeaderRef<'a>(&'a [u8; 48]);
+
+impl<'a> HeaderRef<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self> {
+        bytes
+            .get(..48)
+            .map(|bytes| Self(bytes.try_into().expect("48 bytes")))
+            .ok_or_else(|| DcapError::Parse("Invalid length for Quote Header".into()))
+    }
+
+    pub fn version(&self) -> u16 {
+        u16::from_le_bytes([self.0[0], self.0[1]])
+    }
+
+    pub fn attestation_key_type(&self) -> u16 {
+        u16::from_le_bytes([self.0[2], self.0[3]])
+    }
+
+    pub fn tee_type(&self) -> u32 {
+        u32::from_le_bytes([self.0[4], self.0[5], self.0[6], self.0[7]])
+    }
+
+    pub fn qe_svn(&self) -> u16 {
+        u16::from_le_bytes([self.0[8], self.0[9]])
+    }
+
+    pub fn pce_svn(&self) -> u16 {
+        u16::from_le_bytes([self.0[10], self.0[11]])
+    }
+
+    pub fn qe_vendor_id(&self) -> &'a [u8; 16] {
+        self.0[12..28].try_into().expect("16 bytes")
+    }
+
+    pub fn user_data(&self) -> &'a [u8; 20] {
+        self.0[28..48].try_into().expect("20 bytes")
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8; 48] {
+        self.0
+    }
+
+    /// Copy the header out of the quote.
+    pub fn into_owned(self) -> Header {
+        Header::from_bytes(self.0).expect("48 bytes")
     }
 }
 
 impl Verifiable for Header {
-    type Payload = ();
+    type Payload<'a> = ();
     type Output = ();
+    type Report = ();
 
-    fn verify(&self, _: &Self::Payload) -> Result<()> {
+    fn verify(&self, _: &Self::Payload<'_>) -> Result<()> {
         use hex_literal::hex;
 
-        const SUPPORTED_VERSION: u16 = 3;
         const SUPPORTED_ATTESTATION_KEY_TYPE: u16 = 2;
-        const SUPPORTED_TEE_TYPE: u32 = 0;
-        // Intel® SGX QE Vendor
+        // Intel® SGX QE Vendor, also used by the TD QE
         const VALID_QE_VENDOR_ID: [u8; 16] = hex!("939a7233f79c4ca9940a0db3957f0607");
 
-        if self.version != SUPPORTED_VERSION
+        // TDX quotes only exist from v4 on.
+        let supported_version = match self.tee_type {
+            Self::TEE_TYPE_SGX => (3..=5).contains(&self.version),
+            Self::TEE_TYPE_TDX => (4..=5).contains(&self.version),
+            _ => false,
+        };
+        if !supported_version
             || self.attestation_key_type != SUPPORTED_ATTESTATION_KEY_TYPE
-            || self.tee_type != SUPPORTED_TEE_TYPE
             || self.qe_vendor_id != VALID_QE_VENDOR_ID
         {
-            return Err(anyhow!("Unsupported quote"));
+            return Err(DcapError::Unsupported(
+                "quote version, TEE type, attestation key type or QE vendor".into(),
+            ));
         }
 
         Ok(())
@@ -93,45 +191,73 @@ impl Verifiable for Header {
 ///     isv_svn: 2bytes
 ///     reserved4: 60bytes
 ///     report_data: 64bytes
-
 #[repr(C)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EnclaveReport {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub cpusvn: [u8; 16],
     pub miscselect: u32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub reserved1: [u8; 28],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub attributes: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub mr_enclave: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub reserved2: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub mr_signer: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub reserved3: [u8; 96],
     pub isv_prod_id: u16,
     pub isv_svn: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub reserved4: [u8; 60],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub report_data: [u8; 64],
 }
 
+// The fields add up to the encoded size, without padding.
+const _: () = assert!(size_of::<EnclaveReport>() == 384);
+
 impl BinRepr for EnclaveReport {
     fn from_bytes(bytes: &[u8]) -> Result<Self> {
         if bytes.len() != 384 {
-            return Err(anyhow!("Invalid length for Enclave Report"));
+            return Err(DcapError::Parse("Invalid length for Enclave Report".into()));
         }
 
-        Ok(unsafe {
-            let mut uninit = MaybeUninit::<EnclaveReport>::uninit();
-            std::ptr::copy_nonoverlapping(
-                bytes.as_ptr(),
-                uninit.as_mut_ptr() as *mut u8,
-                bytes.len(),
-            );
-            uninit.assume_init()
+        let mut reader = Reader(bytes);
+        Ok(Self {
+            cpusvn: reader.array(),
+            miscselect: reader.u32(),
+            reserved1: reader.array(),
+            attributes: reader.array(),
+            mr_enclave: reader.array(),
+            reserved2: reader.array(),
+            mr_signer: reader.array(),
+            reserved3: reader.array(),
+            isv_prod_id: reader.u16(),
+            isv_svn: reader.u16(),
+            reserved4: reader.array(),
+            report_data: reader.array(),
         })
     }
 
     fn to_bytes(&self) -> Result<Vec<u8>> {
-        unsafe {
-            let bytes: [u8; 384] = *(self as *const EnclaveReport as *const [u8; 384]);
-            Ok(bytes.to_vec())
-        }
+        let mut bytes = Vec::with_capacity(384);
+        bytes.extend_from_slice(&self.cpusvn);
+        bytes.extend_from_slice(&self.miscselect.to_le_bytes());
+        bytes.extend_from_slice(&self.reserved1);
+        bytes.extend_from_slice(&self.attributes);
+        bytes.extend_from_slice(&self.mr_enclave);
+        bytes.extend_from_slice(&self.reserved2);
+        bytes.extend_from_slice(&self.mr_signer);
+        bytes.extend_from_slice(&self.reserved3);
+        bytes.extend_from_slice(&self.isv_prod_id.to_le_bytes());
+        bytes.extend_from_slice(&self.isv_svn.to_le_bytes());
+        bytes.extend_from_slice(&self.reserved4);
+        bytes.extend_from_slice(&self.report_data);
+        Ok(bytes)
     }
 }
 
@@ -140,131 +266,655 @@ pub type RawSigner = [u8; 32];
 
 impl Verifiable for EnclaveReport {
     type Output = ();
-    type Payload = Option<(Vec<RawEnclaveId>, Vec<RawSigner>)>;
+    type Report = ();
+    type Payload<'a> = EnclavePolicy;
 
-    fn verify(&self, payload: &Self::Payload) -> Result<()> {
-        if payload.is_none() {
-            return Ok(());
-        }
+    fn verify(&self, policy: &Self::Payload<'_>) -> Result<()> {
+        policy.verify_enclave(self)
+    }
+}
+
+/// td_report10_t: 584bytes
+///     tee_tcb_svn: 16bytes
+///     mr_seam: 48bytes
+///     mr_signer_seam: 48bytes
+///     seam_attributes: 8bytes
+///     td_attributes: 8bytes
+///     xfam: 8bytes
+///     mr_td: 48bytes
+///     mr_config_id: 48bytes
+///     mr_owner: 48bytes
+///     mr_owner_config: 48bytes
+///     rtmr: 4 * 48bytes
+///     report_data: 64bytes
+#[repr(C)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TdReport10 {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub tee_tcb_svn: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub mr_seam: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub mr_signer_seam: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub seam_attributes: [u8; 8],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub td_attributes: [u8; 8],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub xfam: [u8; 8],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub mr_td: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub mr_config_id: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub mr_owner: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub mr_owner_config: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::list"))]
+    pub rtmr: [[u8; 48]; 4],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub report_data: [u8; 64],
+}
+
+// The fields add up to the encoded size, without padding.
+const _: () = assert!(size_of::<TdReport10>() == 584);
 
-        let (trusted_enclaves, trusted_signers) = payload.as_ref().unwrap();
-        if !trusted_enclaves.contains(&self.mr_enclave) {
-            return Err(anyhow!("Enclave not trusted"));
+impl BinRepr for TdReport10 {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != 584 {
+            return Err(DcapError::Parse("Invalid length for TD Report".into()));
         }
 
-        if !trusted_signers.contains(&self.mr_signer) {
-            return Err(anyhow!("Signer not trusted"));
+        let mut reader = Reader(bytes);
+        Ok(Self {
+            tee_tcb_svn: reader.array(),
+            mr_seam: reader.array(),
+            mr_signer_seam: reader.array(),
+            seam_attributes: reader.array(),
+            td_attributes: reader.array(),
+            xfam: reader.array(),
+            mr_td: reader.array(),
+            mr_config_id: reader.array(),
+            mr_owner: reader.array(),
+            mr_owner_config: reader.array(),
+            rtmr: [
+                reader.array(),
+                reader.array(),
+                reader.array(),
+                reader.array(),
+            ],
+            report_data: reader.array(),
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(584);
+        bytes.extend_from_slice(&self.tee_tcb_svn);
+        bytes.extend_from_slice(&self.mr_seam);
+        bytes.extend_from_slice(&self.mr_signer_seam);
+        bytes.extend_from_slice(&self.seam_attributes);
+        bytes.extend_from_slice(&self.td_attributes);
+        bytes.extend_from_slice(&self.xfam);
+        bytes.extend_from_slice(&self.mr_td);
+        bytes.extend_from_slice(&self.mr_config_id);
+        bytes.extend_from_slice(&self.mr_owner);
+        bytes.extend_from_slice(&self.mr_owner_config);
+        for rtmr in &self.rtmr {
+            bytes.extend_from_slice(rtmr);
         }
-        Ok(())
+        bytes.extend_from_slice(&self.report_data);
+        Ok(bytes)
     }
 }
 
+impl Verifiable for TdReport10 {
+    type Output = ();
+    type Report = ();
+    type Payload<'a> = EnclavePolicy;
+
+    fn verify(&self, policy: &Self::Payload<'_>) -> Result<()> {
+        policy.verify_td(self)
+    }
+}
+
+/// td_report15_t: 648bytes
+///     td_report: td_report10_t
+///     tee_tcb_svn2: 16bytes
+///     mr_service_td: 48bytes
 #[repr(C)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TdReport15 {
+    pub td_report: TdReport10,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub tee_tcb_svn2: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub mr_service_td: [u8; 48],
+}
+
+impl BinRepr for TdReport15 {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != 648 {
+            return Err(DcapError::Parse("Invalid length for TD Report 1.5".into()));
+        }
+
+        Ok(Self {
+            td_report: TdReport10::from_bytes(&bytes[..584])?,
+            tee_tcb_svn2: bytes[584..600].try_into()?,
+            mr_service_td: bytes[600..648].try_into()?,
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = self.td_report.to_bytes()?;
+        bytes.extend_from_slice(&self.tee_tcb_svn2);
+        bytes.extend_from_slice(&self.mr_service_td);
+        Ok(bytes)
+    }
+}
+
+/// The report a quote attests to, determined by the TEE type of the header and, from v5 on,
+/// by the body type.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ReportBody {
+    Sgx(EnclaveReport),
+    Td10(TdReport10),
+    Td15(TdReport15),
+}
+
+impl ReportBody {
+    /// Body type as encoded in v5 quotes.
+    pub fn body_type(&self) -> u16 {
+        match self {
+            Self::Sgx(_) => 1,
+            Self::Td10(_) => 2,
+            Self::Td15(_) => 3,
+        }
+    }
+
+    pub fn tee_type(&self) -> u32 {
+        match self {
+            Self::Sgx(_) => Header::TEE_TYPE_SGX,
+            Self::Td10(_) | Self::Td15(_) => Header::TEE_TYPE_TDX,
+        }
+    }
+
+    pub fn enclave_report(&self) -> Option<&EnclaveReport> {
+        match self {
+            Self::Sgx(report) => Some(report),
+            _ => None,
+        }
+    }
+
+    /// The TD report, for TD Report 1.5 without the fields it appends.
+    pub fn td_report(&self) -> Option<&TdReport10> {
+        match self {
+            Self::Sgx(_) => None,
+            Self::Td10(report) => Some(report),
+            Self::Td15(report) => Some(&report.td_report),
+        }
+    }
+
+    fn from_typed_bytes(body_type: u16, bytes: &[u8]) -> Result<Self> {
+        Ok(match body_type {
+            1 => Self::Sgx(EnclaveReport::from_bytes(bytes)?),
+            2 => Self::Td10(TdReport10::from_bytes(bytes)?),
+            3 => Self::Td15(TdReport15::from_bytes(bytes)?),
+            _ => {
+                return Err(DcapError::Unsupported(format!(
+                    "quote body type {}",
+                    body_type
+                )))
+            }
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::Sgx(report) => report.to_bytes(),
+            Self::Td10(report) => report.to_bytes(),
+            Self::Td15(report) => report.to_bytes(),
+        }
+    }
+}
+
+/// A [`ReportBody`] borrowed from the encoding of a quote, see [`QuoteRef`].
+///
+/// [`QuoteRef`]: crate::QuoteRef
+#[derive(Clone, Copy, Debug)]
+pub struct ReportRef<'a> {
+    body_type: u16,
+    bytes: &'a [u8],
+}
+
+impl<'a> ReportRef<'a> {
+    /// Borrow a report of the given body type, which must span all of `bytes`.
+    fn new(body_type: u16, bytes: &'a [u8]) -> Result<Self> {
+        let len = match body_type {
+            1 => 384,
+            2 => 584,
+            3 => 648,
+            _ => {
+                return Err(DcapError::Unsupported(format!(
+                    "quote body type {}",
+                    body_type
+                )))
+            }
+        };
+        if bytes.len() != len {
+            return Err(DcapError::Parse("Invalid length for Quote Report".into()));
+        }
+        Ok(Self { body_type, bytes })
+    }
+
+    /// Body type as encoded in v5 quotes, see [`ReportBody::body_type`].
+    pub fn body_type(&self) -> u16 {
+        self.body_type
+    }
+
+    pub fn tee_type(&self) -> u32 {
+        match self.body_type {
+            1 => Header::TEE_TYPE_SGX,
+            _ => Header::TEE_TYPE_TDX,
+        }
+    }
+
+    /// The report data chosen by the enclave or TD.
+    pub fn report_data(&self) -> &'a [u8; 64] {
+        match (self.enclave_report(), self.td_report()) {
+            (Some(report), _) => report.report_data(),
+            (_, Some(report)) => report.report_data(),
+            _ => unreachable!("a report is either an enclave or a TD report"),
+        }
+    }
+
+    pub fn enclave_report(&self) -> Option<EnclaveReportRef<'a>> {
+        (self.body_type == 1).then_some(EnclaveReportRef(self.bytes))
+    }
+
+    /// The TD report, for TD Report 1.5 including the fields it appends.
+    pub fn td_report(&self) -> Option<TdReportRef<'a>> {
+        (self.body_type != 1).then_some(TdReportRef(self.bytes))
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Copy the report out of the quote.
+    pub fn into_owned(self) -> ReportBody {
+        ReportBody::from_typed_bytes(self.body_type, self.bytes).expect("checked report length")
+    }
+}
+
+/// The field of `N` bytes at `offset`.
+fn field<const N: usize>(bytes: &[u8], offset: usize) -> &[u8; N] {
+    bytes[offset..offset + N]
+        .try_into()
+        .expect("field of N bytes")
+}
+
+/// An [`EnclaveReport`] borrowed from the encoding of a quote, see [`ReportRef`].
+#[derive(Clone, Copy, Debug)]
+pub struct EnclaveReportRef<'a>(&'a [u8]);
+
+impl<'a> EnclaveReportRef<'a> {
+    pub fn cpusvn(&self) -> &'a [u8; 16] {
+        field(self.0, 0)
+    }
+
+    pub fn miscselect(&self) -> u32 {
+        u32::from_le_bytes(*field(self.0, 16))
+    }
+
+    pub fn attributes(&self) -> &'a [u8; 16] {
+        field(self.0, 48)
+    }
+
+    pub fn mr_enclave(&self) -> &'a [u8; 32] {
+        field(self.0, 64)
+    }
+
+    pub fn mr_signer(&self) -> &'a [u8; 32] {
+        field(self.0, 128)
+    }
+
+    pub fn isv_prod_id(&self) -> u16 {
+        u16::from_le_bytes(*field(self.0, 256))
+    }
+
+    pub fn isv_svn(&self) -> u16 {
+        u16::from_le_bytes(*field(self.0, 258))
+    }
+
+    pub fn report_data(&self) -> &'a [u8; 64] {
+        field(self.0, 320)
+    }
+}
+
+/// A [`TdReport10`] or [`TdReport15`] borrowed from the encoding of a quote, see
+/// [`ReportRef`].
+#[derive(Clone, Copy, Debug)]
+pub struct TdReportRef<'a>(&'a [u8]);
+
+impl<'a> TdReportRef<'a> {
+    pub fn tee_tcb_svn(&self) -> &'a [u8; 16] {
+        field(self.0, 0)
+    }
+
+    pub fn mr_seam(&self) -> &'a [u8; 48] {
+        field(self.0, 16)
+    }
+
+    pub fn mr_signer_seam(&self) -> &'a [u8; 48] {
+        field(self.0, 64)
+    }
+
+    pub fn seam_attributes(&self) -> &'a [u8; 8] {
+        field(self.0, 112)
+    }
+
+    pub fn td_attributes(&self) -> &'a [u8; 8] {
+        field(self.0, 120)
+    }
+
+    pub fn xfam(&self) -> &'a [u8; 8] {
+        field(self.0, 128)
+    }
+
+    pub fn mr_td(&self) -> &'a [u8; 48] {
+        field(self.0, 136)
+    }
+
+    pub fn mr_config_id(&self) -> &'a [u8; 48] {
+        field(self.0, 184)
+    }
+
+    pub fn mr_owner(&self) -> &'a [u8; 48] {
+        field(self.0, 232)
+    }
+
+    pub fn mr_owner_config(&self) -> &'a [u8; 48] {
+        field(self.0, 280)
+    }
+
+    pub fn rtmr(&self) -> [&'a [u8; 48]; 4] {
+        [0, 1, 2, 3].map(|i| field(self.0, 328 + i * 48))
+    }
+
+    pub fn report_data(&self) -> &'a [u8; 64] {
+        field(self.0, 520)
+    }
+
+    /// Only present in TD Report 1.5.
+    pub fn tee_tcb_svn2(&self) -> Option<&'a [u8; 16]> {
+        (self.0.len() == 648).then(|| field(self.0, 584))
+    }
+
+    /// Only present in TD Report 1.5.
+    pub fn mr_service_td(&self) -> Option<&'a [u8; 48]> {
+        (self.0.len() == 648).then(|| field(self.0, 600))
+    }
+}
+
+/// The signed part of a quote.
+///
+/// Up to v4 the header is directly followed by the report, whose type is given by the TEE type.
+/// From v5 on it is preceded by its body type (2bytes) and size (4bytes).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QuoteBody {
     pub header: Header,
-    pub enclave_report: EnclaveReport,
+    pub report: ReportBody,
 }
 
-impl BinRepr for QuoteBody {
-    fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        if bytes.len() != 432 {
-            return Err(anyhow!("Invalid length for Quote Body"));
+// A v3 SGX quote body is the header followed by the enclave report.
+const _: () = assert!(size_of::<Header>() + size_of::<EnclaveReport>() == 432);
+
+impl QuoteBody {
+    /// Length of the quote body at the start of `bytes`, as announced by its header.
+    pub(crate) fn encoded_len(bytes: &[u8]) -> Result<usize> {
+        if bytes.len() < 48 {
+            return Err(DcapError::Parse("Invalid length for Quote Body".into()));
+        }
+        let header = Header::from_bytes(&bytes[0..48])?;
+        Ok(match (header.version, header.tee_type) {
+            (5, _) => {
+                let size = bytes
+                    .get(50..54)
+                    .ok_or_else(|| DcapError::Parse("Invalid length for Quote Body".into()))?;
+                (u32::from_le_bytes(size.try_into()?) as usize)
+                    .checked_add(48 + 6)
+                    .ok_or_else(|| DcapError::Parse("Invalid length for Quote Body".into()))?
+            }
+            (_, Header::TEE_TYPE_TDX) => 48 + 584,
+            _ => 48 + 384,
+        })
+    }
+
+    /// Borrow the header and report of the encoded quote body `bytes`.
+    pub(crate) fn borrow(bytes: &[u8]) -> Result<(HeaderRef<'_>, ReportRef<'_>)> {
+        if bytes.len() != Self::encoded_len(bytes)? {
+            return Err(DcapError::Parse("Invalid length for Quote Body".into()));
         }
 
+        let header = HeaderRef::new(bytes)?;
+        let bytes = &bytes[48..];
+        let report = match header.version() {
+            5 => {
+                let body_type = u16::from_le_bytes([bytes[0], bytes[1]]);
+                ReportRef::new(body_type, &bytes[6..])?
+            }
+            _ if header.tee_type() == Header::TEE_TYPE_TDX => ReportRef::new(2, bytes)?,
+            _ => ReportRef::new(1, bytes)?,
+        };
+        if report.tee_type() != header.tee_type() {
+            return Err(DcapError::Parse(
+                "Quote body does not match the TEE type".into(),
+            ));
+        }
+        Ok((header, report))
+    }
+
+    /// Verify the header, then the enclave or TD against `policy`.
+    pub(crate) fn verify_policy(&self, policy: &EnclavePolicy) -> StepResult<()> {
+        // STEP1: parse and verify header
+        self.header.verify(&()).at(VerificationStep::Header)?;
+
+        // STEP2: Verify the enclave or TD against the policy
+        match &self.report {
+            ReportBody::Sgx(report) => report.verify(policy),
+            ReportBody::Td10(report) => report.verify(policy),
+            ReportBody::Td15(report) => report.td_report.verify(policy),
+        }
+        .at(VerificationStep::Policy)
+    }
+}
+
+impl BinRepr for QuoteBody {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (header, report) = Self::borrow(bytes)?;
         Ok(Self {
-            header: Header::from_bytes(&bytes[0..48])?,
-            enclave_report: EnclaveReport::from_bytes(&bytes[48..432])?,
+            header: header.into_owned(),
+            report: report.into_owned(),
         })
     }
 
     fn to_bytes(&self) -> Result<Vec<u8>> {
-        let mut bytes = Vec::with_capacity(432);
+        let report = self.report.to_bytes()?;
+
+        let mut bytes = Vec::with_capacity(48 + 6 + report.len());
         bytes.extend_from_slice(&self.header.to_bytes()?);
-        bytes.extend_from_slice(&self.enclave_report.to_bytes()?);
+        if self.header.version == 5 {
+            bytes.extend_from_slice(&self.report.body_type().to_le_bytes());
+            bytes.extend_from_slice(&(report.len() as u32).to_le_bytes());
+        }
+        bytes.extend_from_slice(&report);
         Ok(bytes)
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Quote<Sig> {
     pub body: QuoteBody,
     pub signature: Sig,
 }
 
+/// Split an encoded quote into its body and signature data, without parsing either.
+pub(crate) fn split_quote(bytes: &[u8]) -> Result<(&[u8], &[u8])> {
+    let body_len = QuoteBody::encoded_len(bytes)?;
+    if bytes.len() < body_len.saturating_add(4) {
+        return Err(DcapError::Parse("Invalid length for Quote".into()));
+    }
+    let (body, signature) = bytes.split_at(body_len);
+
+    let signature_len = u32::from_le_bytes(signature[..4].try_into()?) as usize;
+    // The signature data is the last field, anything after it would be unauthenticated.
+    if signature.len() - 4 != signature_len {
+        return Err(DcapError::Parse(
+            "Invalid length for Quote Signature Data".into(),
+        ));
+    }
+    Ok((body, &signature[4..]))
+}
+
 impl<Sig> BinRepr for Quote<Sig>
 where
     Sig: BinRepr,
 {
     fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        if bytes.len() < 432 {
-            return Err(anyhow!("Invalid length for Quote"));
-        }
-        let body = QuoteBody::from_bytes(&bytes[0..432])?;
-        let signature_len = u32::from_le_bytes(bytes[432..436].try_into().unwrap()) as usize;
-
-        let bytes = &bytes[436..];
-        let signature = Sig::from_bytes(&bytes[..signature_len])?;
-        Ok(Self { body, signature })
+        let (body, signature) = split_quote(bytes)?;
+        Ok(Self {
+            body: QuoteBody::from_bytes(body)?,
+            signature: Sig::from_bytes(signature)?,
+        })
     }
 
     fn to_bytes(&self) -> Result<Vec<u8>> {
+        let body = self.body.to_bytes()?;
         let signature = self.signature.to_bytes()?;
 
-        let mut bytes = Vec::with_capacity(432 + 4 + signature.len());
-        bytes.extend_from_slice(&self.body.to_bytes()?);
+        let mut bytes = Vec::with_capacity(body.len() + 4 + signature.len());
+        bytes.extend_from_slice(&body);
         bytes.extend_from_slice(&(signature.len() as u32).to_le_bytes());
-        bytes.extend_from_slice(&self.signature.to_bytes()?);
+        bytes.extend_from_slice(&signature);
         Ok(bytes)
     }
 }
 
-impl<Sig> Verifiable for Quote<Sig>
+impl<Sig> Quote<Sig>
 where
-    Sig: Verifiable<Payload = Vec<u8>>,
+    Sig: for<'a> Verifiable<Payload<'a> = (&'a QuoteBody, &'a Collateral, DateTime<Utc>)>,
 {
-    type Payload = ();
-    type Output = Sig::Output;
-
-    fn verify(&self, _: &Self::Payload) -> Result<()> {
-        // STEP1: parse and verify header
-        self.body.header.verify(&())?;
+    /// Verify the quote against `collateral` and `policy` as of `now`.
+    ///
+    /// Certificates must be within their validity period and the collateral must have been
+    /// issued and not passed its next update at `now`, e.g. the timestamp of the block a quote
+    /// is verified in.
+    pub fn verify_at(
+        &self,
+        collateral: &Collateral,
+        policy: &EnclavePolicy,
+        now: DateTime<Utc>,
+    ) -> Result<Sig::Report> {
+        self.body.verify_policy(policy)?;
+        self.signature.verify(&(&self.body, collateral, now))
+    }
+}
 
-        // STEP2: Verify enclave report MRENCLAVE and MRSIDNER
-        //TODO: pass through the trusted enclave list and signer list
-        self.body.enclave_report.verify(&None)?;
+impl<Sig> Quote<Sig>
+where
+    Sig: for<'a> StepVerifiable<Payload<'a> = (&'a QuoteBody, &'a Collateral, DateTime<Utc>)>,
+{
+    /// Same as [`Quote::verify_at`], also reporting the [`VerificationStep`] a rejected quote
+    /// failed at.
+    pub fn verify_steps_at(
+        &self,
+        collateral: &Collateral,
+        policy: &EnclavePolicy,
+        now: DateTime<Utc>,
+    ) -> StepResult<Sig::Report> {
+        self.body.verify_policy(policy)?;
+        self.signature.verify_steps(&(&self.body, collateral, now))
+    }
+}
 
-        let raw_quote_body = self.body.to_bytes()?;
-        self.signature.verify(&raw_quote_body)?;
+/// Verifies as of the system clock, which `no_std` builds don't have.
+#[cfg(feature = "std")]
+impl<Sig> Verifiable for Quote<Sig>
+where
+    Sig: for<'a> Verifiable<Payload<'a> = (&'a QuoteBody, &'a Collateral, DateTime<Utc>)>,
+{
+    type Payload<'a> = (&'a Collateral, &'a EnclavePolicy);
+    type Output = Sig::Output;
+    type Report = Sig::Report;
 
-        Ok(())
+    /// Verify the quote as of the current system time, see [`Quote::verify_at`].
+    fn verify(&self, (collateral, policy): &Self::Payload<'_>) -> Result<Self::Report> {
+        self.verify_at(collateral, policy, Utc::now())
     }
 
-    fn paramlized(&self, _: &Self::Payload) -> Result<Self::Output> {
-        let raw_quote_body = self.body.to_bytes()?;
-        self.signature.paramlized(&raw_quote_body)
+    fn paramlized(&self, (collateral, _): &Self::Payload<'_>) -> Result<Self::Output> {
+        self.signature
+            .paramlized(&(&self.body, collateral, Utc::now()))
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
+    use anyhow::Result;
     use hex_literal::hex;
 
     use super::*;
-    use crate::auth_data::ECDSAQuoteV3AuthData;
+    use chrono::{DateTime, Utc};
+
+    use crate::{
+        auth_data::{ECDSAQuoteV3AuthData, ECDSAQuoteV4AuthData},
+        enclave::EnclaveIdStatus,
+        tcb_info::TcbStatus,
+        testing::{self, QuoteBuilder, TestCa, TestPlatform},
+    };
 
     const QUOTE_0: [u8; 4734] = hex!("030002000000000009000e00939a7233f79c4ca9940a0db3957f0607ccb12a326354d33986ff47365f17ad4c000000000c0c100fffff0100000000000000000000000000000000000000000000000000000000000000000000000000000000000700000000000000e70000000000000046049af725ec3986eeb788693df7bc5f14d3f2705106a19cd09b9d89237db1a00000000000000000000000000000000000000000000000000000000000000000ef69011f29043f084e99ce420bfebdfa410aee1e132014e7ceff29efa9659bd90000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ca10000084af1f392be216944059f3fa05bf91e1b4e9b513c67493521eb4488af35f49c8f300d57955afc1df97d423c8718ed5b0af82f71047a229df221faa6817ad5daa44131b5c2ed877295959f7333543ba3f17994d767da194a27ba7a4e8a71940118a138dce8499572433c2cc4e4312f92e7144b26f84c59022bfc9aea59967f00d0c0c100fffff0100000000000000000000000000000000000000000000000000000000000000000000000000000000001500000000000000e700000000000000192aa50ce1c0cef03ccf89e7b5b16b0d7978f5c2b1edcf774d87702e8154d8bf00000000000000000000000000000000000000000000000000000000000000008c4f5775d796503e96137f77c68a829a0056ac8ded70140b081b094490c57bff00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000900000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000a654bcd78ffaa5cfc888fc90cbc24fb7f6e19bc8661671f1e3b2cc947db3b6340000000000000000000000000000000000000000000000000000000000000000839adce904d2aec1fc021ad0ec370c7176942d4b64939b95a2e1e1d3e09bf2e57093231f4308b64e8f53b81cd6ae36fc52f202e66ac77b93b13307ee577be36b2000000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f0500620e00002d2d2d2d2d424547494e2043455254494649434154452d2d2d2d2d0a4d494945386a4343424a696741774942416749554b6e314f2b2b58517264456161433535634a4c307470464867336b77436759494b6f5a497a6a3045417749770a634445694d434147413155454177775a535735305a577767553064594946424453794251624746305a6d397962534244515445614d42674741315545436777520a535735305a577767513239796347397959585270623234784644415342674e564241634d43314e68626e526849454e7359584a684d51737743515944565151490a44414a445154454c4d416b474131554542684d4356564d774868634e4d6a4d774f4449304d6a45304d444d775768634e4d7a41774f4449304d6a45304d444d770a576a42774d534977494159445651514444426c4a626e526c624342545231676755454e4c49454e6c636e52705a6d6c6a5958526c4d526f77474159445651514b0a4442464a626e526c6243424462334a7762334a6864476c76626a45554d424947413155454277774c553246756447456751327868636d4578437a414a42674e560a4241674d416b4e424d517377435159445651514745774a56557a425a4d424d4742797147534d34394167454743437147534d34394177454841304941424e47520a727a716c416d4a66617756324b67656a39576e774a736666457868445631756847396e6d57377430505a646e6276732f6c677872584255625657436d5043456f0a4f49587768563673736d6e6b6b48462b576d536a67674d4f4d494944436a416642674e5648534d4547444157674253566231334e765276683655424a796454300a4d383442567776655644427242674e56485238455a4442694d47436758714263686c706f64485277637a6f764c32467761533530636e567a6447566b633256790a646d6c6a5a584d75615735305a577775593239744c334e6e6543396a5a584a3061575a7059324630615739754c33597a4c33426a61324e796244396a595431770a624746305a6d397962535a6c626d4e765a476c755a7a316b5a584977485159445652304f424259454641337234524b62476e54316e584c775a5a7272515559410a4a6b776c4d41344741315564447745422f775145417749477744414d42674e5648524d4241663845416a41414d4949434f77594a4b6f5a496876684e415130420a424949434c444343416967774867594b4b6f5a496876684e415130424151515179753373424e6d7632566643337932772f445344627a434341575547436971470a534962345451454e41514977676746564d42414743797147534962345451454e415149424167454d4d42414743797147534962345451454e415149434167454d0a4d42414743797147534962345451454e41514944416745444d42414743797147534962345451454e41514945416745444d42454743797147534962345451454e0a41514946416749412f7a415242677371686b69472b4530424451454342674943415038774541594c4b6f5a496876684e4151304241676343415145774541594c0a4b6f5a496876684e4151304241676743415141774541594c4b6f5a496876684e4151304241676b43415141774541594c4b6f5a496876684e4151304241676f430a415141774541594c4b6f5a496876684e4151304241677343415141774541594c4b6f5a496876684e4151304241677743415141774541594c4b6f5a496876684e0a4151304241673043415141774541594c4b6f5a496876684e4151304241673443415141774541594c4b6f5a496876684e4151304241673843415141774541594c0a4b6f5a496876684e4151304241684143415141774541594c4b6f5a496876684e4151304241684543415130774877594c4b6f5a496876684e41513042416849450a4541774d4177502f2f7745414141414141414141414141774541594b4b6f5a496876684e4151304241775143414141774641594b4b6f5a496876684e415130420a4241514741474271414141414d41384743697147534962345451454e4151554b415145774867594b4b6f5a496876684e415130424267515136657645326f42790a6f684e362f30727741346d642b6a424542676f71686b69472b453042445145484d4459774541594c4b6f5a496876684e4151304242774542416638774541594c0a4b6f5a496876684e4151304242774942415141774541594c4b6f5a496876684e4151304242774d4241514177436759494b6f5a497a6a304541774944534141770a52514967522b344377346437476a73684848436c7a394c6269785a4a45632f31666c7a734449504d5451437a2b43304349514430516e6d514c2b4e6b4e374a7a0a655a666c5078644734687a374b652b3443595366744b416a48545a7539413d3d0a2d2d2d2d2d454e442043455254494649434154452d2d2d2d2d0a2d2d2d2d2d424547494e2043455254494649434154452d2d2d2d2d0a4d4949436c6a4343416a32674177494241674956414a567658633239472b487051456e4a3150517a7a674658433935554d416f4743437147534d343942414d430a4d476778476a415942674e5642414d4d45556c756447567349464e48574342536232393049454e424d526f77474159445651514b4442464a626e526c624342440a62334a7762334a6864476c76626a45554d424947413155454277774c553246756447456751327868636d4578437a414a42674e564241674d416b4e424d5173770a435159445651514745774a56557a4165467730784f4441314d6a45784d4455774d5442614677307a4d7a41314d6a45784d4455774d5442614d484178496a41670a42674e5642414d4d47556c756447567349464e4857434251513073675547786864475a76636d306751304578476a415942674e5642416f4d45556c75644756730a49454e76636e4276636d4630615739754d5251774567594456515148444174545957353059534244624746795954454c4d416b474131554543417743513045780a437a414a42674e5642415954416c56544d466b77457759484b6f5a497a6a3043415159494b6f5a497a6a304441516344516741454e53422f377432316c58534f0a3243757a7078773734654a423732457944476757357258437478327456544c7136684b6b367a2b5569525a436e71523770734f766771466553786c6d546c4a6c0a65546d693257597a33714f42757a43427544416642674e5648534d4547444157674251695a517a575770303069664f44744a5653763141624f536347724442530a42674e5648523845537a424a4d45656752614244686b466f64485277637a6f764c324e6c636e52705a6d6c6a5958526c63793530636e567a6447566b633256790a646d6c6a5a584d75615735305a577775593239744c306c756447567355306459556d397664454e424c6d526c636a416442674e5648513445466751556c5739640a7a62306234656c4153636e553944504f4156634c336c517744675944565230504151482f42415144416745474d42494741315564457745422f7751494d4159420a4166384341514177436759494b6f5a497a6a30454177494452774177524149675873566b6930772b6936565947573355462f32327561586530594a446a3155650a6e412b546a44316169356343494359623153416d4435786b66545670766f34556f79695359787244574c6d5552344349394e4b7966504e2b0a2d2d2d2d2d454e442043455254494649434154452d2d2d2d2d0a2d2d2d2d2d424547494e2043455254494649434154452d2d2d2d2d0a4d4949436a7a4343416a53674177494241674955496d554d316c71644e496e7a6737535655723951477a6b6e42717777436759494b6f5a497a6a3045417749770a614445614d4267474131554541777752535735305a5777675530645949464a766233516751304578476a415942674e5642416f4d45556c756447567349454e760a636e4276636d4630615739754d5251774567594456515148444174545957353059534244624746795954454c4d416b47413155454341774351304578437a414a0a42674e5642415954416c56544d423458445445344d4455794d5445774e4455784d466f58445451354d54497a4d54497a4e546b314f566f77614445614d4267470a4131554541777752535735305a5777675530645949464a766233516751304578476a415942674e5642416f4d45556c756447567349454e76636e4276636d46300a615739754d5251774567594456515148444174545957353059534244624746795954454c4d416b47413155454341774351304578437a414a42674e56424159540a416c56544d466b77457759484b6f5a497a6a3043415159494b6f5a497a6a3044415163445167414543366e45774d4449595a4f6a2f69505773437a61454b69370a314f694f534c52466857476a626e42564a66566e6b59347533496a6b4459594c304d784f346d717379596a6c42616c54565978465032734a424b357a6c4b4f420a757a43427544416642674e5648534d4547444157674251695a517a575770303069664f44744a5653763141624f5363477244425342674e5648523845537a424a0a4d45656752614244686b466f64485277637a6f764c324e6c636e52705a6d6c6a5958526c63793530636e567a6447566b63325679646d6c6a5a584d75615735300a5a577775593239744c306c756447567355306459556d397664454e424c6d526c636a416442674e564851344546675155496d554d316c71644e496e7a673753560a55723951477a6b6e4271777744675944565230504151482f42415144416745474d42494741315564457745422f7751494d4159424166384341514577436759490a4b6f5a497a6a3045417749445351417752674968414f572f35516b522b533943695344634e6f6f774c7550524c735747662f59693747535839344267775477670a41694541344a306c72486f4d732b586f356f2f7358364f39515778485241765a55474f6452513763767152586171493d0a2d2d2d2d2d454e442043455254494649434154452d2d2d2d2d0a00");
 
     struct MockSig(Vec<u8>);
 
     impl BinRepr for MockSig {
-        fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
             Ok(Self(bytes.to_vec()))
         }
 
-        fn to_bytes(&self) -> Result<Vec<u8>> {
+        fn to_bytes(&self) -> crate::Result<Vec<u8>> {
             Ok(self.0.clone())
         }
     }
 
+    #[test]
+    fn test_little_endian_layout() -> Result<()> {
+        let bytes: Vec<u8> = (0..48).collect();
+        let header = Header::from_bytes(&bytes)?;
+        assert_eq!(header.version, 0x0100);
+        assert_eq!(header.attestation_key_type, 0x0302);
+        assert_eq!(header.tee_type, 0x07060504);
+        assert_eq!(header.pce_svn, 0x0b0a);
+        assert_eq!(header.user_data[19], 47);
+        assert_eq!(header.to_bytes()?, bytes);
+
+        let bytes: Vec<u8> = (0..384).map(|i| i as u8).collect();
+        let report = EnclaveReport::from_bytes(&bytes)?;
+        assert_eq!(report.miscselect, 0x13121110);
+        assert_eq!(report.mr_enclave[0], 64);
+        assert_eq!(report.isv_prod_id, 0x0100);
+        assert_eq!(report.isv_svn, 0x0302);
+        assert_eq!(report.report_data[63], 127);
+        assert_eq!(report.to_bytes()?, bytes);
+
+        let bytes: Vec<u8> = (0..584).map(|i| i as u8).collect();
+        let report = TdReport10::from_bytes(&bytes)?;
+        assert_eq!(report.rtmr[3][47], (519 % 256) as u8);
+        assert_eq!(report.to_bytes()?, bytes);
+        Ok(())
+    }
+
     #[test]
     fn test_quote_from_bytes_with_mock_sig() -> Result<()> {
         let quote = Quote::<MockSig>::from_bytes(&QUOTE_0)?;
@@ -273,12 +923,541 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_quote_serde() -> Result<()> {
+        let quote = Quote::<ECDSAQuoteV3AuthData>::from_bytes(&QUOTE_0)?;
+        let json = serde_json::to_value(&quote)?;
+        assert_eq!(json["body"]["header"]["version"], 3);
+        assert_eq!(
+            json["body"]["report"]["sgx"]["mr_enclave"],
+            "46049af725ec3986eeb788693df7bc5f14d3f2705106a19cd09b9d89237db1a0"
+        );
+        assert_eq!(json["signature"]["qe_cert"]["cert_type"], 5);
+
+        let decoded: Quote<ECDSAQuoteV3AuthData> = serde_json::from_value(json)?;
+        assert_eq!(decoded.to_bytes()?, QUOTE_0);
+        Ok(())
+    }
+
     #[test]
     fn test_quote_from_bytes_with_ecdsa_sig() -> Result<()> {
         let quote = Quote::<ECDSAQuoteV3AuthData>::from_bytes(&QUOTE_0)?;
         let bytes = quote.to_bytes()?;
         assert_eq!(bytes.as_slice(), QUOTE_0.as_slice());
-        quote.verify(&())?;
+
+        // The fixture was taken from a debug enclave.
+        let collateral = testing::intel_collateral();
+        let err = quote
+            .verify_at(
+                &collateral,
+                &EnclavePolicy::default(),
+                testing::verification_time(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            DcapError::Policy("Enclave attributes not allowed".into())
+        );
+        let policy = EnclavePolicy::default()
+            .allow_debug()
+            .trust_enclave(quote.body.report.enclave_report().unwrap().mr_enclave);
+        let report = quote.verify_at(&collateral, &policy, testing::verification_time())?;
+        assert_eq!(report.fmspc, hex!("00606a000000"));
+        assert_eq!(report.tcb_status(), TcbStatus::SWHardeningNeeded);
+        assert_eq!(
+            report.tcb_level.tcb_date,
+            "2023-08-09T00:00:00Z".parse::<DateTime<Utc>>()?
+        );
+        assert!(report.advisory_ids().is_empty());
+        assert_eq!(report.qe_tcb_level.tcb_status, EnclaveIdStatus::UpToDate);
+
+        // The chain in the quote is anchored at Intel, not at the test root.
+        assert!(quote
+            .verify_at(
+                &testing::collateral(),
+                &policy,
+                testing::verification_time()
+            )
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_verify_at() -> Result<()> {
+        let platform = TestPlatform::new();
+        let body = platform.quote_body(3);
+        let signature = platform.sign(&body);
+        let quote = Quote { body, signature };
+        let collateral = testing::collateral();
+        let policy = EnclavePolicy::default();
+        let at = |raw: &str| raw.parse::<DateTime<Utc>>();
+
+        // The collateral was issued 2023-09-26 and is due for an update on 2023-10-26.
+        assert!(quote
+            .verify_at(&collateral, &policy, at("2023-10-26T00:00:00Z")?)
+            .is_ok());
+        for now in ["2023-09-25T00:00:00Z", "2023-10-27T00:00:00Z"] {
+            let err = quote.verify_at(&collateral, &policy, at(now)?).unwrap_err();
+            assert!(matches!(err, DcapError::CollateralExpired(_)), "{}", err);
+        }
+        // Verifying at the current time fails as the bundled collateral has long expired.
+        assert!(matches!(
+            quote.verify(&(&collateral, &policy)),
+            Err(DcapError::CollateralExpired(_))
+        ));
+        Ok(())
+    }
+
+    /// The test collateral with every TCB level raised above the platform's PCE SVN.
+    fn raised_collateral() -> Result<Collateral> {
+        let signer = TestCa::tcb_signer();
+        let mut tcb_info: serde_json::Value =
+            serde_json::from_str(include_str!("../assets/tcbinfo.json"))?;
+        for level in tcb_info["tcbInfo"]["tcbLevels"].as_array_mut().unwrap() {
+            level["tcb"]["pcesvn"] = 99.into();
+        }
+        Ok(Collateral::with_root_ca(
+            signer.issuers[0].clone(),
+            &[signer.sign_json(&tcb_info.to_string())],
+            signer.sign_json(include_str!("../assets/identity.json")),
+            signer.chain_pem(),
+        )?)
+    }
+
+    #[test]
+    fn test_quote_verify_fails_closed_without_matching_tcb() -> Result<()> {
+        let quote = QuoteBuilder::sgx().build();
+        let err = quote
+            .verify_at(
+                &raised_collateral()?,
+                &EnclavePolicy::default(),
+                testing::verification_time(),
+            )
+            .unwrap_err();
+        assert_eq!(err, DcapError::TcbNotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_verify_steps() -> Result<()> {
+        let collateral = testing::collateral();
+        let policy = EnclavePolicy::default();
+        let now = testing::verification_time();
+        let platform = TestPlatform::new();
+        let body = platform.quote_body(3);
+        let signature = platform.sign(&body);
+        let mut quote = Quote { body, signature };
+        assert!(quote.verify_steps_at(&collateral, &policy, now).is_ok());
+
+        quote.signature.ecdsa256_bit_signature[0] ^= 1;
+        let err = quote
+            .verify_steps_at(&collateral, &policy, now)
+            .unwrap_err();
+        assert_eq!(err.step, VerificationStep::QuoteSignature);
+        assert_eq!(
+            err.step.to_string(),
+            "STEP9 (verify the attestation signature)"
+        );
+
+        // Like an unknown version, one the signature data doesn't come with is unsupported.
+        quote.body.header.version = 4;
+        let err = quote
+            .verify_steps_at(&collateral, &policy, now)
+            .unwrap_err();
+        assert_eq!(err.step, VerificationStep::Header);
+        assert!(matches!(err.error, DcapError::Unsupported(_)));
+
+        // A debug enclave at a TCB below every level of the raised TCB Info.
+        let quote = QuoteBuilder::sgx()
+            .enclave_report(|report| report.attributes[0] |= 0x02)
+            .build();
+        let collateral = raised_collateral()?;
+        let err = quote
+            .verify_steps_at(&collateral, &policy, now)
+            .unwrap_err();
+        assert_eq!(err.step, VerificationStep::Policy);
+        let err = quote
+            .verify_steps_at(&collateral, &policy.allow_debug(), now)
+            .unwrap_err();
+        assert_eq!(err.step, VerificationStep::Tcb);
+        assert_eq!(err.error, DcapError::TcbNotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_verify_qe_identity_status() -> Result<()> {
+        let verify = |collateral: &Collateral, qe_svn| {
+            let mut platform = TestPlatform::new();
+            platform.qe_svn = qe_svn;
+            let body = platform.quote_body(3);
+            let signature = platform.sign(&body);
+            Quote { body, signature }.verify_at(
+                collateral,
+                &EnclavePolicy::default(),
+                testing::verification_time(),
+            )
+        };
+        let collateral = testing::collateral();
+
+        // An out of date QE lowers the platform status.
+        let report = verify(&collateral, 7)?;
+        assert_eq!(report.qe_tcb_level.tcb_status, EnclaveIdStatus::OutOfDate);
+        assert_eq!(report.tcb_level.tcb_status, TcbStatus::SWHardeningNeeded);
+        assert_eq!(report.tcb_status(), TcbStatus::OutOfDate);
+        assert_eq!(verify(&collateral, 0).unwrap_err(), DcapError::TcbNotFound);
+
+        let mut collateral = testing::collateral();
+        let revoked = include_str!("../assets/identity.json").replace("OutOfDate", "Revoked");
+        collateral.set_qe_identity(TestCa::tcb_signer().sign_json(&revoked))?;
+        assert_eq!(
+            verify(&collateral, 8)?.tcb_status(),
+            TcbStatus::SWHardeningNeeded
+        );
+        assert_eq!(
+            verify(&collateral, 7).unwrap_err(),
+            DcapError::TcbStatus(TcbStatus::Revoked)
+        );
+
+        // Advisories of the platform and QE TCB levels are merged.
+        let signer = TestCa::tcb_signer();
+        let tcb_info = include_str!("../assets/tcbinfo.json").replace(
+            r#""tcbStatus": "SWHardeningNeeded""#,
+            r#""tcbStatus": "SWHardeningNeeded", "advisoryIDs": ["INTEL-SA-00615", "INTEL-SA-00657"]"#,
+        );
+        let qe_identity = include_str!("../assets/identity.json").replace(
+            r#""tcbStatus": "OutOfDate""#,
+            r#""tcbStatus": "OutOfDate", "advisoryIDs": ["INTEL-SA-00657", "INTEL-SA-00767"]"#,
+        );
+        let mut collateral = testing::collateral();
+        collateral.add_tcb_info(signer.sign_json(&tcb_info))?;
+        collateral.set_qe_identity(signer.sign_json(&qe_identity))?;
+        let report = verify(&collateral, 7)?;
+        assert_eq!(report.qe_tcb_level.isvsvn, 6);
+        assert_eq!(
+            report.advisory_ids(),
+            ["INTEL-SA-00615", "INTEL-SA-00657", "INTEL-SA-00767"]
+        );
+        assert_eq!(
+            verify(&collateral, 8)?.advisory_ids(),
+            ["INTEL-SA-00615", "INTEL-SA-00657"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_v4_round_trip() -> Result<()> {
+        let platform = TestPlatform::new();
+        let body = platform.quote_body(4);
+        let signature = ECDSAQuoteV4AuthData::new(platform.sign(&body));
+        let bytes = Quote { body, signature }.to_bytes()?;
+
+        // The QE Report Certification Data (type 6) follows the attestation key.
+        assert_eq!(&bytes[436 + 128..436 + 130], &6u16.to_le_bytes());
+        let quote = Quote::<ECDSAQuoteV4AuthData>::from_bytes(&bytes)?;
+        assert_eq!(quote.body.header.version, 4);
+        assert_eq!(quote.to_bytes()?, bytes);
+
+        let mut unsupported = bytes.clone();
+        unsupported[436 + 128] = 5;
+        assert!(Quote::<ECDSAQuoteV4AuthData>::from_bytes(&unsupported).is_err());
+
+        // It nests the PCK chain (type 5) after the QE report, its signature and auth data.
+        let qe_auth_data_size =
+            u16::from_le_bytes([bytes[436 + 134 + 448], bytes[436 + 134 + 449]]);
+        let nested = 436 + 134 + 450 + qe_auth_data_size as usize;
+        assert_eq!(&bytes[nested..nested + 2], &5u16.to_le_bytes());
+        let mut unsupported = bytes.clone();
+        unsupported[nested] = 3;
+        assert!(matches!(
+            Quote::<ECDSAQuoteV4AuthData>::from_bytes(&unsupported),
+            Err(DcapError::Unsupported(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_v4_verify() -> Result<()> {
+        let platform = TestPlatform::new();
+        let body = platform.quote_body(4);
+        let signature = ECDSAQuoteV4AuthData::new(platform.sign(&body));
+        let quote =
+            Quote::<ECDSAQuoteV4AuthData>::from_bytes(&Quote { body, signature }.to_bytes()?)?;
+
+        let report = quote.verify_at(
+            &testing::collateral(),
+            &EnclavePolicy::default(),
+            testing::verification_time(),
+        )?;
+        assert_eq!(report.fmspc, TestPlatform::FMSPC);
+        assert_eq!(report.tcb_status(), TcbStatus::SWHardeningNeeded);
+        assert_eq!(report.qe_tcb_level.tcb_status, EnclaveIdStatus::UpToDate);
+
+        // The PCK chain is anchored at the test root, not at Intel.
+        assert!(quote
+            .verify_at(
+                &testing::intel_collateral(),
+                &EnclavePolicy::default(),
+                testing::verification_time()
+            )
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_truncated() {
+        for len in 0..QUOTE_0.len() {
+            match Quote::<ECDSAQuoteV3AuthData>::from_bytes(&QUOTE_0[..len]) {
+                Err(DcapError::Parse(_)) => {}
+                Err(err) => panic!("{}: unexpected error {}", len, err),
+                Ok(_) => panic!("{}: truncated quote parsed", len),
+            }
+        }
+    }
+
+    #[test]
+    fn test_quote_oversized() -> Result<()> {
+        let mut bytes = QUOTE_0.to_vec();
+        bytes.push(0);
+        assert!(Quote::<ECDSAQuoteV3AuthData>::from_bytes(&bytes).is_err());
+
+        // A v5 body announcing more bytes than the quote holds
+        let platform = TestPlatform::new();
+        let mut bytes = platform.quote_body(5).to_bytes()?;
+        bytes[50..54].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(QuoteBody::from_bytes(&bytes).is_err());
+        assert!(Quote::<MockSig>::from_bytes(&bytes).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_version_must_match_signature_data() -> Result<()> {
+        let platform = TestPlatform::new();
+
+        let body = platform.quote_body(3);
+        let signature = platform.sign(&body);
+        let quote = Quote { body, signature };
+        quote.verify_at(
+            &testing::collateral(),
+            &EnclavePolicy::default(),
+            testing::verification_time(),
+        )?;
+        let v4 = Quote::<ECDSAQuoteV4AuthData>::from_bytes(
+            &Quote {
+                body: quote.body,
+                signature: ECDSAQuoteV4AuthData::new(quote.signature),
+            }
+            .to_bytes()?,
+        )?;
+        assert!(v4
+            .verify_at(
+                &testing::collateral(),
+                &EnclavePolicy::default(),
+                testing::verification_time()
+            )
+            .is_err());
+
+        let body = platform.quote_body(4);
+        let signature = platform.sign(&body);
+        let quote = Quote { body, signature };
+        assert!(quote
+            .verify_at(
+                &testing::collateral(),
+                &EnclavePolicy::default(),
+                testing::verification_time()
+            )
+            .is_err());
+        Ok(())
+    }
+
+    fn tdx_quote(version: u16, tee_tcb_svn: [u8; 16]) -> Result<Quote<ECDSAQuoteV4AuthData>> {
+        let platform = TestPlatform::tdx();
+        let mut body = platform.quote_body(version);
+        match &mut body.report {
+            ReportBody::Td10(report) => report.tee_tcb_svn = tee_tcb_svn,
+            ReportBody::Td15(report) => report.td_report.tee_tcb_svn = tee_tcb_svn,
+            ReportBody::Sgx(_) => unreachable!(),
+        }
+        let signature = ECDSAQuoteV4AuthData::new(platform.sign(&body));
+        Ok(Quote::from_bytes(&Quote { body, signature }.to_bytes()?)?)
+    }
+
+    #[test]
+    fn test_tdx_quote_round_trip() -> Result<()> {
+        let quote = tdx_quote(4, TestPlatform::TEE_TCB_SVN)?;
+        let bytes = quote.to_bytes()?;
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into()?), 0x81);
+        assert!(matches!(quote.body.report, ReportBody::Td10(_)));
+        assert_eq!(quote.body.report.td_report().unwrap().mr_td, [0x33; 48]);
+        assert_eq!(
+            Quote::<ECDSAQuoteV4AuthData>::from_bytes(&bytes)?.to_bytes()?,
+            bytes
+        );
+
+        // v5 announces the body type and size ahead of the TD Report 1.5.
+        let quote = tdx_quote(5, TestPlatform::TEE_TCB_SVN)?;
+        let bytes = quote.to_bytes()?;
+        assert_eq!(&bytes[48..54], &[3, 0, 0x88, 2, 0, 0]);
+        assert!(matches!(quote.body.report, ReportBody::Td15(_)));
+        assert_eq!(
+            Quote::<ECDSAQuoteV4AuthData>::from_bytes(&bytes)?.to_bytes()?,
+            bytes
+        );
+
+        // An SGX body type does not fit a TDX header.
+        let mut mismatched = bytes.clone();
+        mismatched[48] = 1;
+        assert!(Quote::<ECDSAQuoteV4AuthData>::from_bytes(&mismatched).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tdx_quote_verify() -> Result<()> {
+        let collateral = testing::collateral();
+        for version in [4, 5] {
+            let report = tdx_quote(version, TestPlatform::TEE_TCB_SVN)?.verify_at(
+                &collateral,
+                &EnclavePolicy::default(),
+                testing::tdx_verification_time(),
+            )?;
+            assert_eq!(report.fmspc, TestPlatform::TDX_FMSPC);
+            assert_eq!(report.tcb_status(), TcbStatus::UpToDate);
+            assert_eq!(report.qe_tcb_level.tcb_status, EnclaveIdStatus::UpToDate);
+        }
+
+        // The TDX collateral was issued after the SGX collateral expired.
+        let err = tdx_quote(4, TestPlatform::TEE_TCB_SVN)?
+            .verify_at(
+                &collateral,
+                &EnclavePolicy::default(),
+                testing::verification_time(),
+            )
+            .unwrap_err();
+        assert!(matches!(err, DcapError::CollateralExpired(_)));
+
+        // The TDX TCB components are matched against the TEE TCB SVN of the TD report.
+        let mut tee_tcb_svn = TestPlatform::TEE_TCB_SVN;
+        tee_tcb_svn[2] = 2;
+        let report = tdx_quote(4, tee_tcb_svn)?.verify_at(
+            &collateral,
+            &EnclavePolicy::default(),
+            testing::tdx_verification_time(),
+        )?;
+        assert_eq!(report.tcb_status(), TcbStatus::OutOfDate);
+        assert_eq!(report.advisory_ids(), ["INTEL-SA-00837"]);
+        tee_tcb_svn[2] = 1;
+        let err = tdx_quote(4, tee_tcb_svn)?
+            .verify_at(
+                &collateral,
+                &EnclavePolicy::default(),
+                testing::tdx_verification_time(),
+            )
+            .unwrap_err();
+        assert_eq!(err, DcapError::TcbNotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tdx_quote_verify_module_identity() -> Result<()> {
+        let collateral = testing::collateral();
+        let verify = |tee_tcb_svn| {
+            tdx_quote(4, tee_tcb_svn).unwrap().verify_at(
+                &collateral,
+                &EnclavePolicy::default(),
+                testing::tdx_verification_time(),
+            )
+        };
+
+        // TDX module 1.3, matched against the TDX_01 identity.
+        let mut tee_tcb_svn = [3, 1, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let report = verify(tee_tcb_svn)?;
+        assert_eq!(report.tcb_status(), TcbStatus::UpToDate);
+        let module_level = report.tdx_module_tcb_level.as_ref().unwrap();
+        assert_eq!(module_level.isvsvn, 3);
+
+        // An out of date module lowers the platform status.
+        tee_tcb_svn[0] = 2;
+        let report = verify(tee_tcb_svn)?;
+        assert_eq!(report.tcb_level.tcb_status, TcbStatus::UpToDate);
+        assert_eq!(report.tcb_status(), TcbStatus::OutOfDate);
+
+        tee_tcb_svn[0] = 1;
+        assert_eq!(verify(tee_tcb_svn).unwrap_err(), DcapError::TcbNotFound);
+
+        // No identity for TDX module 2.x.
+        tee_tcb_svn = [3, 2, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert!(matches!(
+            verify(tee_tcb_svn).unwrap_err(),
+            DcapError::CollateralMismatch(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tdx_quote_requires_tdx_collateral() -> Result<()> {
+        let platform = TestPlatform::tdx();
+
+        // A TD report from a TDX module not signed by Intel.
+        let mut body = platform.quote_body(4);
+        if let ReportBody::Td10(report) = &mut body.report {
+            report.mr_signer_seam = [0xff; 48];
+        }
+        let signature = ECDSAQuoteV4AuthData::new(platform.sign(&body));
+        let err = Quote { body, signature }
+            .verify_at(
+                &testing::collateral(),
+                &EnclavePolicy::default(),
+                testing::tdx_verification_time(),
+            )
+            .unwrap_err();
+        assert!(matches!(err, DcapError::CollateralMismatch(_)));
+
+        // Without a TD QE identity TDX quotes cannot be verified.
+        let mut bundle = testing::bundle();
+        bundle.td_qe_identity = None;
+        let collateral = bundle.collateral_with_root_ca(platform.pck.issuers[1].clone())?;
+        let err = tdx_quote(4, TestPlatform::TEE_TCB_SVN)?
+            .verify_at(
+                &collateral,
+                &EnclavePolicy::default(),
+                testing::tdx_verification_time(),
+            )
+            .unwrap_err();
+        assert!(matches!(err, DcapError::CollateralMismatch(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_verify_enclave_policy() -> Result<()> {
+        let platform = TestPlatform::new();
+        let body = platform.quote_body(3);
+        let signature = platform.sign(&body);
+        let quote = Quote { body, signature };
+        let collateral = testing::collateral();
+
+        let policy = EnclavePolicy::default()
+            .trust_enclave([0x11; 32])
+            .trust_signer([0x22; 32]);
+        assert!(quote
+            .verify_at(&collateral, &policy, testing::verification_time())
+            .is_ok());
+        let policy = EnclavePolicy::default().trust_enclave([0x33; 32]);
+        let err = quote
+            .verify_at(&collateral, &policy, testing::verification_time())
+            .unwrap_err();
+        assert_eq!(err, DcapError::Policy("Enclave not trusted".into()));
+        let policy = EnclavePolicy::default().min_isv_svn(0, 1);
+        let err = quote
+            .verify_at(&collateral, &policy, testing::verification_time())
+            .unwrap_err();
+        assert_eq!(err, DcapError::Policy("Enclave ISVSVN is too low".into()));
+
+        // SGX measurements cannot be satisfied by a TD.
+        let quote = tdx_quote(4, TestPlatform::TEE_TCB_SVN)?;
+        let policy = EnclavePolicy::default().trust_signer([0x22; 32]);
+        assert!(quote
+            .verify_at(&collateral, &policy, testing::verification_time())
+            .is_err());
         Ok(())
     }
 }
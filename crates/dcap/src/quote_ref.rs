@@ -0,0 +1,318 @@
+use alloc::format;
+
+use chrono::{DateTime, Utc};
+use sha2::{digest::Digest, Sha256};
+use x509_cert::Certificate;
+
+use super::{
+    auth_data::AuthDataRef,
+    collateral::Collateral,
+    error::{DcapError, Result, StepResult},
+    policy::EnclavePolicy,
+    quote::{split_quote, HeaderRef, QuoteBody, ReportRef},
+    report::VerificationReport,
+    signature::EcdsaParams,
+};
+
+/// A quote of any version, borrowed from its encoding.
+///
+/// Parsing checks the layout like [`Quote::from_bytes`](crate::Quote), but nothing is copied
+/// out of `bytes` and verification hashes the signed range of `bytes` as is instead of
+/// re-encoding the body.
+pub struct QuoteRef<'a> {
+    bytes: &'a [u8],
+    body_len: usize,
+    header: HeaderRef<'a>,
+    report: ReportRef<'a>,
+    auth_data: AuthDataRef<'a>,
+}
+
+impl<'a> QuoteRef<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let (body, signature) = split_quote(bytes)?;
+        let (header, report) = QuoteBody::borrow(body)?;
+        let auth_data = match header.version() {
+            3 => AuthDataRef::from_v3_bytes(signature)?,
+            4 | 5 => AuthDataRef::from_v4_bytes(signature)?,
+            version => return Err(DcapError::Unsupported(format!("quote version {}", version))),
+        };
+
+        Ok(Self {
+            bytes,
+            body_len: body.len(),
+            header,
+            report,
+            auth_data,
+        })
+    }
+
+    /// The whole encoded quote.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn header(&self) -> HeaderRef<'a> {
+        self.header
+    }
+
+    pub fn report(&self) -> ReportRef<'a> {
+        self.report
+    }
+
+    /// Copy the header and report out of the quote.
+    pub fn to_body(&self) -> QuoteBody {
+        QuoteBody {
+            header: self.header.into_owned(),
+            report: self.report.into_owned(),
+        }
+    }
+
+    /// The encoded quote body, i.e. the bytes signed by the attestation key.
+    pub fn signed_bytes(&self) -> &'a [u8] {
+        &self.bytes[..self.body_len]
+    }
+
+    /// SHA-256 of [`QuoteRef::signed_bytes`].
+    pub fn body_hash(&self) -> [u8; 32] {
+        Sha256::digest(self.signed_bytes()).into()
+    }
+
+    pub fn auth_data(&self) -> AuthDataRef<'a> {
+        self.auth_data
+    }
+
+    /// PEM encoded PCK certificate chain.
+    pub fn cert_chain_pem(&self) -> &'a [u8] {
+        self.auth_data.cert_data
+    }
+
+    pub fn certs(&self) -> Result<[Certificate; 3]> {
+        self.auth_data.certs()
+    }
+
+    /// The four P-256 verifications of the quote, see [`ECDSAQuoteV3AuthData::ecdsa_params`].
+    ///
+    /// [`ECDSAQuoteV3AuthData::ecdsa_params`]: crate::ECDSAQuoteV3AuthData::ecdsa_params
+    pub fn ecdsa_params(&self) -> Result<[EcdsaParams; 4]> {
+        self.auth_data.ecdsa_params(self.signed_bytes())
+    }
+
+    /// Verify the quote against `collateral` and `policy` as of `now`, see
+    /// [`Quote::verify_at`](crate::Quote::verify_at).
+    pub fn verify_at(
+        &self,
+        collateral: &Collateral,
+        policy: &EnclavePolicy,
+        now: DateTime<Utc>,
+    ) -> Result<VerificationReport> {
+        Ok(self.verify_steps_at(collateral, policy, now)?)
+    }
+
+    /// Same as [`QuoteRef::verify_at`], also reporting the [`VerificationStep`] a rejected
+    /// quote failed at.
+    ///
+    /// [`VerificationStep`]: crate::VerificationStep
+    pub fn verify_steps_at(
+        &self,
+        collateral: &Collateral,
+        policy: &EnclavePolicy,
+        now: DateTime<Utc>,
+    ) -> StepResult<VerificationReport> {
+        let body = self.to_body();
+        body.verify_policy(policy)?;
+        self.auth_data
+            .verify_signature_data(&body.report, self.signed_bytes(), collateral, now)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::{
+        auth_data::{ECDSAQuoteV3AuthData, ECDSAQuoteV4AuthData},
+        error::VerificationStep,
+        quote::{EnclaveReport, Quote, ReportBody, TdReport10, TdReport15},
+        testing::{self, TestPlatform},
+        traits::BinRepr,
+    };
+
+    fn encode(platform: &TestPlatform, version: u16) -> Result<Vec<u8>> {
+        let body = platform.quote_body(version);
+        let signature = platform.sign(&body);
+        Ok(match version {
+            3 => Quote { body, signature }.to_bytes()?,
+            _ => Quote {
+                body,
+                signature: ECDSAQuoteV4AuthData::new(signature),
+            }
+            .to_bytes()?,
+        })
+    }
+
+    #[test]
+    fn test_quote_ref_matches_quote() -> Result<()> {
+        // Counting bytes, so a field read at the wrong offset doesn't match.
+        let counting = |len: usize| (0..len).map(|i| i as u8).collect::<Vec<_>>();
+        let platform = TestPlatform::new();
+        let mut body = platform.quote_body(3);
+        body.report = ReportBody::Sgx(EnclaveReport::from_bytes(&counting(384))?);
+        let signature = platform.sign(&body);
+        let bytes = Quote { body, signature }.to_bytes()?;
+        let quote = Quote::<ECDSAQuoteV3AuthData>::from_bytes(&bytes)?;
+        let quote_ref = QuoteRef::parse(&bytes)?;
+
+        let signed = quote.body.to_bytes()?;
+        assert_eq!(quote_ref.signed_bytes(), signed.as_slice());
+        assert_eq!(
+            quote_ref.body_hash(),
+            <[u8; 32]>::from(Sha256::digest(&signed))
+        );
+        let (header, report) = (quote_ref.header(), quote_ref.report());
+        assert_eq!(header.as_bytes().as_slice(), quote.body.header.to_bytes()?);
+        assert_eq!(header.version(), quote.body.header.version);
+        assert_eq!(header.tee_type(), quote.body.header.tee_type);
+        assert_eq!(header.qe_svn(), quote.body.header.qe_svn);
+        assert_eq!(header.pce_svn(), quote.body.header.pce_svn);
+        assert_eq!(header.qe_vendor_id(), &quote.body.header.qe_vendor_id);
+        assert_eq!(header.user_data(), &quote.body.header.user_data);
+        assert_eq!(report.body_type(), quote.body.report.body_type());
+        assert_eq!(report.as_bytes(), &signed[48..]);
+        let (enclave_report, owned) = (
+            report.enclave_report().unwrap(),
+            quote.body.report.enclave_report().unwrap(),
+        );
+        assert_eq!(enclave_report.cpusvn(), &owned.cpusvn);
+        assert_eq!(enclave_report.miscselect(), owned.miscselect);
+        assert_eq!(enclave_report.attributes(), &owned.attributes);
+        assert_eq!(enclave_report.mr_enclave(), &owned.mr_enclave);
+        assert_eq!(enclave_report.mr_signer(), &owned.mr_signer);
+        assert_eq!(enclave_report.isv_prod_id(), owned.isv_prod_id);
+        assert_eq!(enclave_report.isv_svn(), owned.isv_svn);
+        assert_eq!(enclave_report.report_data(), &owned.report_data);
+        assert_eq!(report.report_data(), &owned.report_data);
+        assert!(report.td_report().is_none());
+        assert_eq!(quote_ref.to_body().to_bytes()?, signed);
+        assert_eq!(
+            quote_ref.auth_data().into_owned().to_bytes()?,
+            quote.signature.to_bytes()?
+        );
+        assert_eq!(
+            quote_ref.cert_chain_pem(),
+            quote.signature.qe_cert.cert_data
+        );
+        assert_eq!(quote_ref.certs()?, quote.signature.qe_cert.certs()?);
+        assert_eq!(
+            quote_ref.ecdsa_params()?.map(|params| params.msghash),
+            quote
+                .signature
+                .ecdsa_params(&quote.body)?
+                .map(|params| params.msghash)
+        );
+        // The header, report and signature data point into the input.
+        let range = bytes.as_ptr_range();
+        assert!(range.contains(&header.as_bytes().as_ptr()));
+        assert!(range.contains(&report.as_bytes().as_ptr()));
+        assert!(range.contains(&quote_ref.auth_data().raw_qe_report.as_ptr()));
+        assert!(range.contains(&quote_ref.cert_chain_pem().as_ptr()));
+
+        let platform = TestPlatform::tdx();
+        for (version, report) in [
+            (4, ReportBody::Td10(TdReport10::from_bytes(&counting(584))?)),
+            (5, ReportBody::Td15(TdReport15::from_bytes(&counting(648))?)),
+        ] {
+            let mut body = platform.quote_body(version);
+            body.report = report;
+            let signature = ECDSAQuoteV4AuthData::new(platform.sign(&body));
+            let bytes = Quote { body, signature }.to_bytes()?;
+            let quote = Quote::<ECDSAQuoteV4AuthData>::from_bytes(&bytes)?;
+            let report = QuoteRef::parse(&bytes)?.report();
+
+            let (td_report, owned) = (
+                report.td_report().unwrap(),
+                quote.body.report.td_report().unwrap(),
+            );
+            assert_eq!(td_report.tee_tcb_svn(), &owned.tee_tcb_svn);
+            assert_eq!(td_report.mr_seam(), &owned.mr_seam);
+            assert_eq!(td_report.mr_signer_seam(), &owned.mr_signer_seam);
+            assert_eq!(td_report.seam_attributes(), &owned.seam_attributes);
+            assert_eq!(td_report.td_attributes(), &owned.td_attributes);
+            assert_eq!(td_report.xfam(), &owned.xfam);
+            assert_eq!(td_report.mr_td(), &owned.mr_td);
+            assert_eq!(td_report.mr_config_id(), &owned.mr_config_id);
+            assert_eq!(td_report.mr_owner(), &owned.mr_owner);
+            assert_eq!(td_report.mr_owner_config(), &owned.mr_owner_config);
+            assert_eq!(td_report.rtmr(), owned.rtmr.each_ref());
+            assert_eq!(td_report.report_data(), &owned.report_data);
+            assert_eq!(report.report_data(), &owned.report_data);
+            assert!(report.enclave_report().is_none());
+            match &quote.body.report {
+                ReportBody::Td15(owned) => {
+                    assert_eq!(td_report.tee_tcb_svn2(), Some(&owned.tee_tcb_svn2));
+                    assert_eq!(td_report.mr_service_td(), Some(&owned.mr_service_td));
+                }
+                _ => {
+                    assert_eq!(td_report.tee_tcb_svn2(), None);
+                    assert_eq!(td_report.mr_service_td(), None);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_ref_verify() -> Result<()> {
+        let collateral = testing::collateral();
+        let policy = EnclavePolicy::default();
+        for (platform, version, fmspc) in [
+            (TestPlatform::new(), 3, TestPlatform::FMSPC),
+            (TestPlatform::new(), 4, TestPlatform::FMSPC),
+            (TestPlatform::new(), 5, TestPlatform::FMSPC),
+            (TestPlatform::tdx(), 4, TestPlatform::TDX_FMSPC),
+        ] {
+            let bytes = encode(&platform, version)?;
+            let now = platform.verification_time();
+            let report = QuoteRef::parse(&bytes)?.verify_at(&collateral, &policy, now)?;
+            assert_eq!(report.fmspc, fmspc, "v{}", version);
+
+            let quote_ref = QuoteRef::parse(&bytes)?;
+            let body = quote_ref.to_body();
+            let report_data = match body.report.enclave_report() {
+                Some(report) => report.report_data,
+                None => body.report.td_report().unwrap().report_data,
+            };
+            assert_eq!(
+                quote_ref.report().report_data(),
+                &report_data,
+                "v{}",
+                version
+            );
+
+            // The signed range is hashed as is, so any change to it breaks the signature.
+            let mut tampered = bytes.clone();
+            tampered[QuoteRef::parse(&bytes)?.signed_bytes().len() - 1] ^= 1;
+            let err = QuoteRef::parse(&tampered)?
+                .verify_steps_at(&collateral, &policy, now)
+                .unwrap_err();
+            assert_eq!(err.step, VerificationStep::QuoteSignature, "v{}", version);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_ref_truncated() -> Result<()> {
+        let bytes = encode(&TestPlatform::new(), 4)?;
+        for len in 0..bytes.len() {
+            assert!(
+                matches!(QuoteRef::parse(&bytes[..len]), Err(DcapError::Parse(_))),
+                "{}",
+                len
+            );
+        }
+        let mut oversized = bytes.clone();
+        oversized.push(0);
+        assert!(QuoteRef::parse(&oversized).is_err());
+        Ok(())
+    }
+}
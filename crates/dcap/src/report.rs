@@ -0,0 +1,61 @@
+use super::{
+    enclave::{EnclaveIdStatus, TcbLevel},
+    tcb_info::{TcbLevelInfo, TcbStatus, TdxModuleTcbLevel},
+};
+use alloc::{string::String, vec::Vec};
+
+/// Outcome of a successful quote verification.
+///
+/// Verification only fails outright on invalid evidence, a revoked TCB or a TCB not covered by
+/// the TCB Info, everything else is reported here so callers can decide whether to accept, warn
+/// or reject.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerificationReport {
+    /// FMSPC of the platform the quote was generated on.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub fmspc: [u8; 6],
+    /// The TCB level of the platform's TCB Info matched by the PCK certificate.
+    pub tcb_level: TcbLevelInfo,
+    /// The TCB level of the TDX module, for TD quotes whose TCB Info identifies the module
+    /// version.
+    pub tdx_module_tcb_level: Option<TdxModuleTcbLevel>,
+    /// The TCB level of the QE Identity matched by the QE report.
+    pub qe_tcb_level: TcbLevel,
+}
+
+impl VerificationReport {
+    /// The overall TCB status: the platform TCB status, lowered to out of date when the QE or
+    /// the TDX module is.
+    pub fn tcb_status(&self) -> TcbStatus {
+        let qe_out_of_date = self.qe_tcb_level.tcb_status == EnclaveIdStatus::OutOfDate;
+        let tdx_module_out_of_date = (self.tdx_module_tcb_level.as_ref())
+            .is_some_and(|level| level.tcb_status == TcbStatus::OutOfDate);
+        let status = self.tcb_level.tcb_status;
+        if !(qe_out_of_date || tdx_module_out_of_date) {
+            return status;
+        }
+        match status {
+            TcbStatus::UpToDate | TcbStatus::SWHardeningNeeded => TcbStatus::OutOfDate,
+            TcbStatus::ConfigurationNeeded | TcbStatus::ConfigurationAndSWHardeningNeeded => {
+                TcbStatus::OutOfDateConfigurationNeeded
+            }
+            status => status,
+        }
+    }
+
+    /// The advisories of the platform, QE and TDX module TCB levels, without duplicates.
+    pub fn advisory_ids(&self) -> Vec<String> {
+        let mut advisory_ids: Vec<String> = Vec::new();
+        let tdx_module = self.tdx_module_tcb_level.iter();
+        for id in (self.tcb_level.advisory_ids.iter())
+            .chain(&self.qe_tcb_level.advisory_ids)
+            .chain(tdx_module.flat_map(|level| &level.advisory_ids))
+        {
+            if !advisory_ids.contains(id) {
+                advisory_ids.push(id.clone());
+            }
+        }
+        advisory_ids
+    }
+}
@@ -0,0 +1,95 @@
+//! Hex (de)serialization of byte strings, for use with `#[serde(with = "...")]`.
+//!
+//! PCS collateral encodes identifiers and masks as lowercase hex without prefix, and the
+//! `serde` feature uses the same encoding for every byte array of quotes and reports.
+
+use alloc::{format, string::String, vec::Vec};
+use serde::{de::Error, Deserialize, Deserializer};
+
+#[cfg(feature = "serde")]
+pub(crate) fn serialize<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: AsRef<[u8]>,
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&hex::encode(bytes))
+}
+
+pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: TryFrom<Vec<u8>>,
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    let bytes = hex::decode(&raw).map_err(D::Error::custom)?;
+    let len = bytes.len();
+    T::try_from(bytes).map_err(|_| D::Error::custom(format!("unexpected length {}", len)))
+}
+
+/// Optional byte strings, `null` when absent.
+#[cfg(feature = "serde")]
+pub(crate) mod option {
+    use alloc::vec::Vec;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<T, S>(bytes: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: AsRef<[u8]>,
+        S: Serializer,
+    {
+        match bytes {
+            Some(bytes) => super::serialize(bytes, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: TryFrom<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Hex<T: TryFrom<Vec<u8>>>(#[serde(deserialize_with = "super::deserialize")] T);
+
+        Ok(Option::<Hex<T>>::deserialize(deserializer)?.map(|Hex(bytes)| bytes))
+    }
+}
+
+/// Fixed-size lists of byte strings, e.g. the RTMRs of a TD report.
+#[cfg(feature = "serde")]
+pub(crate) mod list {
+    use alloc::{format, vec::Vec};
+    use serde::{de::Error, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S, const N: usize, const M: usize>(
+        list: &[[u8; N]; M],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(M))?;
+        for bytes in list {
+            seq.serialize_element(&hex::encode(bytes))?;
+        }
+        seq.end()
+    }
+
+    pub(crate) fn deserialize<'de, D, const N: usize, const M: usize>(
+        deserializer: D,
+    ) -> Result<[[u8; N]; M], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Hex<const N: usize>(#[serde(deserialize_with = "super::deserialize")] [u8; N]);
+
+        let list = Vec::<Hex<N>>::deserialize(deserializer)?;
+        let len = list.len();
+        list.into_iter()
+            .map(|Hex(bytes)| bytes)
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| D::Error::custom(format!("unexpected length {}", len)))
+    }
+}
@@ -1,4 +1,5 @@
-use anyhow::{anyhow, Result};
+use super::error::{DcapError, Result};
+use alloc::{format, vec, vec::Vec};
 use p256::EncodedPoint;
 use sha2::{digest::Digest, Sha256};
 use signature::hazmat::PrehashVerifier;
@@ -12,18 +13,21 @@ where
     let message = Sha256::digest(message.as_ref());
     let message = message.as_slice();
 
-    vk.verify_prehash(message.as_ref(), sig.as_ref()).unwrap();
-    Ok(())
+    vk.verify_prehash(message, sig.as_ref())
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EcdsaParams {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub vk: [u8; 65],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub signature: [u8; 64],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub msghash: [u8; 32],
 }
 
-impl std::fmt::Debug for EcdsaParams {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for EcdsaParams {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
             "EcdsaParams {{\n  vk: 0x{},\n  signature: 0x{},\n  msghash: 0x{},\n}}",
@@ -34,8 +38,8 @@ impl std::fmt::Debug for EcdsaParams {
     }
 }
 
-impl std::fmt::Display for EcdsaParams {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for EcdsaParams {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let mut ret = vec![];
         ret.extend_from_slice(&self.vk);
         ret.extend_from_slice(&self.signature);
@@ -60,34 +64,39 @@ pub struct VerifyingKey(pub p256::ecdsa::VerifyingKey);
 
 impl VerifyingKey {
     pub fn verify_prehash(&self, msg: impl AsRef<[u8]>, sig: impl AsRef<[u8]>) -> Result<()> {
+        let sig = p256::ecdsa::Signature::from_slice(sig.as_ref())
+            .map_err(|_| DcapError::Parse("Malformed signature".into()))?;
         self.0
-            .verify_prehash(
-                msg.as_ref(),
-                &p256::ecdsa::Signature::from_slice(sig.as_ref()).unwrap(),
-            )
-            .map_err(|_| anyhow!("Invalid signature"))
+            .verify_prehash(msg.as_ref(), &sig)
+            .map_err(|_| DcapError::Signature("Signature does not verify".into()))
     }
 
     pub fn from_untagged_bytes(bytes: impl AsRef<[u8]>) -> Result<Self> {
         let len = bytes.as_ref().len();
         if len != 64 {
-            return Err(anyhow!("Expect 64 bytes but found {}", len));
+            return Err(DcapError::Parse(format!(
+                "Expect 64 bytes but found {}",
+                len
+            )));
         }
 
         let point = EncodedPoint::from_untagged_bytes(bytes.as_ref().into());
         let vk = p256::ecdsa::VerifyingKey::from_encoded_point(&point)
-            .map_err(|_| anyhow!("Invalid public key"))?;
+            .map_err(|_| DcapError::Parse("Invalid public key".into()))?;
         Ok(Self(vk))
     }
 
     pub fn from_sec1_bytes(bytes: impl AsRef<[u8]>) -> Result<Self> {
         let len = bytes.as_ref().len();
         if len != 65 {
-            return Err(anyhow!("Expect 65 bytes but found {}", len));
+            return Err(DcapError::Parse(format!(
+                "Expect 65 bytes but found {}",
+                len
+            )));
         }
 
         let vk = p256::ecdsa::VerifyingKey::from_sec1_bytes(bytes.as_ref())
-            .map_err(|_| anyhow!("Invalid public key"))?;
+            .map_err(|_| DcapError::Parse("Invalid public key".into()))?;
         Ok(Self(vk))
     }
 
@@ -101,7 +110,7 @@ impl VerifyingKey {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
     use base64::{engine::general_purpose, Engine as _};
@@ -111,11 +120,20 @@ mod tests {
         let sig = general_purpose::STANDARD.decode("ieckK3oL6Z98ZoqL28H8r2+nVi3ShTjbq0sFnp1pVcLENFk9PMsOflgl7/sU4lHm5e+3ONYEJkftLi+qyRkXGA==").unwrap();
         let public_key = general_purpose::STANDARD.decode("zY/a5X6fzGY4t+C98c/m60eDwp7RORbxDBIccLcXPdYSkUIvnvaKG2p+nMy+fMLAc4+BqZb35i6QlMH4C8DXiA==").unwrap();
         let message = general_purpose::STANDARD.decode("AwACAAAAAAAJAA4Ak5pyM/ecTKmUCg2zlX8GB58v1eFSi0gAaM+d5aI7bOgAAAAADAwQD///AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABQAAAAAAAADnAAAAAAAAAIDIDmlGKK7n/RSYZe+/CUN2f7i1mn9geBRujaQ/DuCcAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD3qZTlRpxL2bFOOSxHx+5xQ2RZXsc8AJVR6ASCh+1pIQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAU35qIoBd1R7of7n6YauZYZvq3Pbz8zV6CrDg2pf3twptTNQBJFgiN7ltTFPLQQDuECHomN3HoW3ougA49Y7Ud").unwrap();
+        assert!(verify_signature(
+            &VerifyingKey::from_untagged_bytes(&public_key).unwrap(),
+            &sig,
+            &message
+        )
+        .is_ok());
+
+        let mut tampered = message;
+        tampered[0] ^= 1;
         assert!(verify_signature(
             &VerifyingKey::from_untagged_bytes(public_key).unwrap(),
             sig,
-            message
+            tampered
         )
-        .is_ok());
+        .is_err());
     }
 }
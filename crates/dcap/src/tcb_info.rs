@@ -1,130 +1,554 @@
-use std::{convert::TryInto, str::FromStr};
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+use core::{cmp::Reverse, convert::TryInto, str::FromStr};
 
-use anyhow::{anyhow, Result};
+use super::{
+    error::{DcapError, Result},
+    serde_hex,
+};
 use chrono::{DateTime, Utc};
-use serde_json::Value;
+use serde::Deserialize;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TcbInfo {
+    pub id: TcbInfoId,
     pub version: u8,
     pub issue_data: DateTime<Utc>,
     pub next_update: DateTime<Utc>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub fmspc: [u8; 6],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub pce_id: [u8; 2],
     pub tcb_type: u8,
     pub tcb_evaluation_data_number: u8,
+    /// Identity of the TDX module, only present in TDX TCB Info.
+    pub tdx_module: Option<TdxModule>,
+    /// Identities of the TDX module per major version, only present in TDX TCB Info.
+    pub tdx_module_identities: Vec<TdxModuleIdentity>,
+    /// In document order, which Intel gives from the highest TCB level down. Levels are only
+    /// partially ordered by their SVNs, so unlike the TDX module levels they are not sorted:
+    /// a platform is matched to the first level it meets, which relies on that order.
     pub tcb_levels: Vec<TcbLevelInfo>,
 }
 
+/// The TEE a TCB Info document applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "UPPERCASE"))]
+pub enum TcbInfoId {
+    Sgx,
+    Tdx,
+}
+
+impl FromStr for TcbInfoId {
+    type Err = DcapError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "SGX" => Ok(Self::Sgx),
+            "TDX" => Ok(Self::Tdx),
+            _ => Err(DcapError::Parse(format!("Unknown tcb info id {}", s))),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TdxModule {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub mrsigner: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub attributes: [u8; 8],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub attributes_mask: [u8; 8],
+}
+
+impl TdxModule {
+    /// Check the signer and attributes of the TDX module that produced a TD report.
+    pub fn matches(&self, mr_signer_seam: &[u8; 48], seam_attributes: &[u8; 8]) -> bool {
+        let attributes_matched = (seam_attributes.iter())
+            .zip(self.attributes.iter())
+            .zip(self.attributes_mask.iter())
+            .all(|((a, b), m)| (a & m) == (*b));
+        *mr_signer_seam == self.mrsigner && attributes_matched
+    }
+}
+
+/// Identity and TCB levels of one major version of the TDX module.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TdxModuleIdentity {
+    /// `TDX_` followed by the major version in hex, e.g. `TDX_01`.
+    pub id: String,
+    pub module: TdxModule,
+    /// Sorted from the highest ISVSVN down.
+    pub tcb_levels: Vec<TdxModuleTcbLevel>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TdxModuleTcbLevel {
+    /// Compared against the minor version at index 0 of the TEE TCB SVN.
+    pub isvsvn: u8,
+    pub tcb_date: DateTime<Utc>,
+    pub tcb_status: TcbStatus,
+    pub advisory_ids: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TcbLevelInfo {
-    pub tcb: Tcb,
+    pub tcb: TcbLevelSvns,
     pub tcb_date: DateTime<Utc>,
-    pub tcb_statue: String,
+    pub tcb_status: TcbStatus,
+    pub advisory_ids: Vec<String>,
 }
 
-pub struct Tcb {
-    pub sgxtcbcompsvn: [u8; 16],
+/// Status of a TCB level as defined by the Intel PCS.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TcbStatus {
+    UpToDate,
+    SWHardeningNeeded,
+    ConfigurationNeeded,
+    ConfigurationAndSWHardeningNeeded,
+    OutOfDate,
+    OutOfDateConfigurationNeeded,
+    Revoked,
+}
+
+impl TcbStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UpToDate => "UpToDate",
+            Self::SWHardeningNeeded => "SWHardeningNeeded",
+            Self::ConfigurationNeeded => "ConfigurationNeeded",
+            Self::ConfigurationAndSWHardeningNeeded => "ConfigurationAndSWHardeningNeeded",
+            Self::OutOfDate => "OutOfDate",
+            Self::OutOfDateConfigurationNeeded => "OutOfDateConfigurationNeeded",
+            Self::Revoked => "Revoked",
+        }
+    }
+}
+
+impl FromStr for TcbStatus {
+    type Err = DcapError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "UpToDate" => Self::UpToDate,
+            "SWHardeningNeeded" => Self::SWHardeningNeeded,
+            "ConfigurationNeeded" => Self::ConfigurationNeeded,
+            "ConfigurationAndSWHardeningNeeded" => Self::ConfigurationAndSWHardeningNeeded,
+            "OutOfDate" => Self::OutOfDate,
+            "OutOfDateConfigurationNeeded" => Self::OutOfDateConfigurationNeeded,
+            "Revoked" => Self::Revoked,
+            _ => return Err(DcapError::Parse(format!("Unknown tcb status {}", s))),
+        })
+    }
+}
+
+impl core::fmt::Display for TcbStatus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The component SVNs and PCE SVN a TCB level requires, see [`TcbLevelInfo`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TcbLevelSvns {
+    pub sgxtcbcomponents: [TcbComponent; 16],
     pub pcesvn: u16,
+    /// Compared against the TEE TCB SVN of a TD report, only present in TDX TCB Info.
+    pub tdxtcbcomponents: Option<[TcbComponent; 16]>,
+}
+
+impl TcbLevelSvns {
+    pub fn sgxtcbcompsvn(&self) -> [u8; 16] {
+        self.sgxtcbcomponents.each_ref().map(|c| c.svn)
+    }
+
+    pub fn tdxtcbcompsvn(&self) -> Option<[u8; 16]> {
+        (self.tdxtcbcomponents.as_ref()).map(|components| components.each_ref().map(|c| c.svn))
+    }
+}
+
+/// A TCB component of a TCB level. TCB Info v2 identifies components by their index only, v3
+/// adds what they cover.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TcbComponent {
+    pub svn: u8,
+    /// E.g. `BIOS` or `OS/VMM`.
+    pub category: Option<String>,
+    /// E.g. `Early Microcode Update` or `TDX Module`.
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub component_type: Option<String>,
 }
 
 impl TcbInfo {
-    pub fn get() -> &'static Self {
-        use std::sync::OnceLock;
-        static TCB_INFO: OnceLock<TcbInfo> = OnceLock::new();
-        TCB_INFO.get_or_init(|| TcbInfo::load().unwrap())
+    /// The identity of the TDX module with `major_version`, as found at index 1 of the TEE TCB
+    /// SVN.
+    pub fn tdx_module_identity(&self, major_version: u8) -> Option<&TdxModuleIdentity> {
+        let id = format!("TDX_{:02X}", major_version);
+        self.tdx_module_identities
+            .iter()
+            .find(|identity| identity.id == id)
     }
 
-    fn load() -> Result<Self> {
-        let raw = include_str!("../assets/tcbinfo.json");
-        let value: Value = serde_json::from_str(raw)?;
-        let value = value.get("tcbInfo").unwrap();
+    /// Parse a TCB Info document as served by the Intel PCS `tcb` endpoint.
+    pub fn load(raw: impl AsRef<[u8]>) -> Result<Self> {
+        let TcbInfoDocument { tcb_info: json } = serde_json::from_slice(raw.as_ref())?;
 
-        let load_datetime = |key: &str| {
-            let raw = value.get(key).unwrap().as_str().unwrap();
-            DateTime::<Utc>::from_str(raw).unwrap()
-        };
-        let load_number = |key: &str| value.get(key).unwrap().as_u64().unwrap();
-
-        macro_rules! load_hex {
-            ($name: expr) => {
-                value
-                    .get($name)
-                    .and_then(|v| v.as_str())
-                    .and_then(|v| hex::decode(v.as_bytes()).ok())
-                    .and_then(|v| TryInto::try_into(v.as_slice()).ok())
-                    .ok_or(anyhow!("{} doesn't exist or cannot be parsed", $name))
-            };
+        // Only present from TCB Info v3 on, earlier versions cover SGX only.
+        let id = json
+            .id
+            .as_deref()
+            .map(str::parse)
+            .transpose()?
+            .unwrap_or(TcbInfoId::Sgx);
+        if id == TcbInfoId::Tdx && json.tdx_module.is_none() {
+            return Err(DcapError::Parse("tdxModule doesn't exist".into()));
         }
 
-        let version = load_number("version") as u8;
-        let issue_data = load_datetime("issueDate");
-        let next_update = load_datetime("nextUpdate");
-        let fmspc = load_hex!("fmspc")?;
-        let pce_id = load_hex!("pceId")?;
-        let tcb_type = load_number("tcbType") as u8;
-        let tcb_evaluation_data_number = load_number("tcbEvaluationDataNumber") as u8;
-
-        let tcb_levels = value
-            .get("tcbLevels")
-            .unwrap()
-            .as_array()
-            .unwrap()
-            .iter()
-            .map(|value| {
-                let load_u16 = |key: &str| {
-                    let value = value.get("tcb").unwrap();
-                    value.get(key).unwrap().as_u64().unwrap() as u16
-                };
-
-                let tcb = Tcb {
-                    sgxtcbcompsvn: (1..=16)
-                        .map(|idx| load_u16(&format!("sgxtcbcomp{:02}svn", idx)) as u8)
-                        .collect::<Vec<_>>()
-                        .as_slice()
-                        .try_into()
-                        .unwrap(),
-                    pcesvn: load_u16("pcesvn"),
-                };
-                let tcb_date = value
-                    .get("tcbDate")
-                    .unwrap()
-                    .as_str()
-                    .unwrap()
-                    .parse()
-                    .unwrap();
-                let tcb_statue = value
-                    .get("tcbStatus")
-                    .unwrap()
-                    .as_str()
-                    .unwrap()
-                    .to_string();
-
-                TcbLevelInfo {
-                    tcb,
-                    tcb_date,
-                    tcb_statue,
+        let tcb_levels = json
+            .tcb_levels
+            .into_iter()
+            .map(|level| {
+                let tcb = level.tcb.into_svns()?;
+                if id == TcbInfoId::Tdx && tcb.tdxtcbcomponents.is_none() {
+                    return Err(DcapError::Parse("tdxtcbcomponents doesn't exist".into()));
                 }
+                Ok(TcbLevelInfo {
+                    tcb,
+                    tcb_date: level.tcb_date,
+                    tcb_status: level.tcb_status.parse()?,
+                    advisory_ids: level.advisory_ids,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let tdx_module_identities = json
+            .tdx_module_identities
+            .into_iter()
+            .map(|identity| {
+                let mut tcb_levels = identity
+                    .tcb_levels
+                    .into_iter()
+                    .map(|level| {
+                        Ok(TdxModuleTcbLevel {
+                            isvsvn: level.tcb.isvsvn,
+                            tcb_date: level.tcb_date,
+                            tcb_status: level.tcb_status.parse()?,
+                            advisory_ids: level.advisory_ids,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                tcb_levels.sort_by_key(|level| Reverse(level.isvsvn));
+                Ok(TdxModuleIdentity {
+                    id: identity.id,
+                    module: identity.module.into(),
+                    tcb_levels,
+                })
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         Ok(Self {
-            version,
-            issue_data,
-            next_update,
-            fmspc,
-            pce_id,
-            tcb_type,
-            tcb_evaluation_data_number,
+            id,
+            version: json.version,
+            issue_data: json.issue_date,
+            next_update: json.next_update,
+            fmspc: json.fmspc,
+            pce_id: json.pce_id,
+            tcb_type: json.tcb_type,
+            tcb_evaluation_data_number: json.tcb_evaluation_data_number,
+            tdx_module: json.tdx_module.map(Into::into),
+            tdx_module_identities,
             tcb_levels,
         })
     }
+
+    /// Check that this TCB Info has been issued and not passed its next update at `now`.
+    pub fn verify_validity(&self, now: DateTime<Utc>) -> Result<()> {
+        if now < self.issue_data {
+            return Err(DcapError::CollateralExpired(
+                "TCB Info is not yet issued".into(),
+            ));
+        }
+        if now > self.next_update {
+            return Err(DcapError::CollateralExpired(
+                "TCB Info has passed its next update".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A TCB Info document in the format of the PCS `tcb` endpoint.
+#[derive(Deserialize)]
+struct TcbInfoDocument {
+    #[serde(rename = "tcbInfo")]
+    tcb_info: TcbInfoJson,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcbInfoJson {
+    id: Option<String>,
+    version: u8,
+    issue_date: DateTime<Utc>,
+    next_update: DateTime<Utc>,
+    #[serde(deserialize_with = "serde_hex::deserialize")]
+    fmspc: [u8; 6],
+    #[serde(deserialize_with = "serde_hex::deserialize")]
+    pce_id: [u8; 2],
+    tcb_type: u8,
+    tcb_evaluation_data_number: u8,
+    tdx_module: Option<TdxModuleJson>,
+    /// Only present in TDX TCB Info from v3 on.
+    #[serde(default)]
+    tdx_module_identities: Vec<TdxModuleIdentityJson>,
+    tcb_levels: Vec<TcbLevelJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TdxModuleJson {
+    #[serde(deserialize_with = "serde_hex::deserialize")]
+    mrsigner: [u8; 48],
+    #[serde(deserialize_with = "serde_hex::deserialize")]
+    attributes: [u8; 8],
+    #[serde(deserialize_with = "serde_hex::deserialize")]
+    attributes_mask: [u8; 8],
+}
+
+impl From<TdxModuleJson> for TdxModule {
+    fn from(json: TdxModuleJson) -> Self {
+        Self {
+            mrsigner: json.mrsigner,
+            attributes: json.attributes,
+            attributes_mask: json.attributes_mask,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TdxModuleIdentityJson {
+    id: String,
+    #[serde(flatten)]
+    module: TdxModuleJson,
+    tcb_levels: Vec<TdxModuleTcbLevelJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TdxModuleTcbLevelJson {
+    tcb: TdxModuleTcbJson,
+    tcb_date: DateTime<Utc>,
+    tcb_status: String,
+    #[serde(rename = "advisoryIDs", default)]
+    advisory_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TdxModuleTcbJson {
+    isvsvn: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcbLevelJson {
+    tcb: TcbJson,
+    tcb_date: DateTime<Utc>,
+    tcb_status: String,
+    /// Only present from TCB Info v3 on.
+    #[serde(rename = "advisoryIDs", default)]
+    advisory_ids: Vec<String>,
+}
+
+/// TCB Info v3 lists the components as arrays of `{"svn": .., "category": .., "type": ..}`
+/// objects, v2 as `sgxtcbcompNNsvn` members.
+#[derive(Deserialize)]
+struct TcbJson {
+    sgxtcbcomponents: Option<Vec<TcbComponentJson>>,
+    tdxtcbcomponents: Option<Vec<TcbComponentJson>>,
+    pcesvn: u16,
+    #[serde(flatten)]
+    sgxtcbcompsvn: BTreeMap<String, u8>,
+}
+
+#[derive(Deserialize)]
+struct TcbComponentJson {
+    svn: u8,
+    category: Option<String>,
+    #[serde(rename = "type")]
+    component_type: Option<String>,
+}
+
+impl From<TcbComponentJson> for TcbComponent {
+    fn from(json: TcbComponentJson) -> Self {
+        Self {
+            svn: json.svn,
+            category: json.category,
+            component_type: json.component_type,
+        }
+    }
+}
+
+impl TcbJson {
+    fn into_svns(self) -> Result<TcbLevelSvns> {
+        let sgxtcbcomponents = match self.sgxtcbcomponents {
+            Some(components) => components.into_iter().map(Into::into).collect(),
+            None => (1..=16)
+                .map(|idx| {
+                    let key = format!("sgxtcbcomp{:02}svn", idx);
+                    let svn = self
+                        .sgxtcbcompsvn
+                        .get(&key)
+                        .copied()
+                        .ok_or_else(|| DcapError::Parse(format!("{} doesn't exist", key)))?;
+                    Ok(TcbComponent {
+                        svn,
+                        ..Default::default()
+                    })
+                })
+                .collect::<Result<_>>()?,
+        };
+        Ok(TcbLevelSvns {
+            sgxtcbcomponents: components_array(sgxtcbcomponents, "sgxtcbcomponents")?,
+            pcesvn: self.pcesvn,
+            tdxtcbcomponents: self
+                .tdxtcbcomponents
+                .map(|components| {
+                    let components = components.into_iter().map(Into::into).collect();
+                    components_array(components, "tdxtcbcomponents")
+                })
+                .transpose()?,
+        })
+    }
 }
 
-#[cfg(test)]
+fn components_array(components: Vec<TcbComponent>, key: &str) -> Result<[TcbComponent; 16]> {
+    components
+        .try_into()
+        .map_err(|_| DcapError::Parse(format!("{} cannot be parsed", key)))
+}
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_parse_tcb_info_json() {
-        TcbInfo::get();
+    fn test_tcb_info_validity() -> Result<()> {
+        let tcb_info = TcbInfo::load(include_str!("../assets/tcbinfo.json"))?;
+        // Issued 2023-09-26T04:43:52Z, next update 2023-10-26T04:43:52Z
+        assert!(tcb_info
+            .verify_validity("2023-10-01T00:00:00Z".parse().unwrap())
+            .is_ok());
+        assert!(tcb_info
+            .verify_validity("2023-09-26T00:00:00Z".parse().unwrap())
+            .is_err());
+        assert!(tcb_info
+            .verify_validity("2023-10-27T00:00:00Z".parse().unwrap())
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tcb_info_json() -> Result<()> {
+        let tcb_info = TcbInfo::load(include_str!("../assets/tcbinfo.json"))?;
+        assert_eq!(tcb_info.fmspc, hex_literal::hex!("00606a000000"));
+        assert_eq!(
+            tcb_info.tcb_levels[0].tcb_status,
+            TcbStatus::SWHardeningNeeded
+        );
+        assert!(tcb_info.tcb_levels[0].advisory_ids.is_empty());
+        assert_eq!(tcb_info.id, TcbInfoId::Sgx);
+        assert!(tcb_info.tdx_module.is_none());
+        assert!(tcb_info.tcb_levels[0].tcb.tdxtcbcomponents.is_none());
+        assert!(tcb_info.tdx_module_identities.is_empty());
+        // v2 identifies components by index only.
+        let component = &tcb_info.tcb_levels[0].tcb.sgxtcbcomponents[0];
+        assert_eq!(component.svn, 12);
+        assert_eq!(component.category, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tdx_tcb_info_json() -> Result<()> {
+        let tcb_info = TcbInfo::load(include_str!("../assets/tdx_tcbinfo.json"))?;
+        assert_eq!(tcb_info.id, TcbInfoId::Tdx);
+        assert_eq!(tcb_info.version, 3);
+        assert_eq!(tcb_info.tdx_module.as_ref().unwrap().mrsigner, [0; 48]);
+
+        let level = &tcb_info.tcb_levels[0];
+        assert_eq!(
+            level.tcb.sgxtcbcompsvn(),
+            [2, 2, 2, 2, 3, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(level.tcb.pcesvn, 11);
+        assert_eq!(
+            level.tcb.tdxtcbcompsvn(),
+            Some([3, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(tcb_info.tcb_levels[1].advisory_ids, ["INTEL-SA-00837"]);
+        let component = &level.tcb.tdxtcbcomponents.as_ref().unwrap()[0];
+        assert_eq!(component.category.as_deref(), Some("OS/VMM"));
+        assert_eq!(component.component_type.as_deref(), Some("TDX Module"));
+
+        let identity = tcb_info.tdx_module_identity(1).unwrap();
+        assert_eq!(identity.id, "TDX_01");
+        assert_eq!(identity.module.attributes_mask, [0xff; 8]);
+        assert_eq!(identity.tcb_levels[1].isvsvn, 2);
+        assert_eq!(identity.tcb_levels[1].tcb_status, TcbStatus::OutOfDate);
+        assert_eq!(identity.tcb_levels[1].advisory_ids, ["INTEL-SA-00837"]);
+        assert!(tcb_info.tdx_module_identity(2).is_none());
+
+        // TDX TCB Info without the TDX module identity is rejected.
+        let raw = include_str!("../assets/tdx_tcbinfo.json").replace("tdxModule", "unknown");
+        assert!(TcbInfo::load(raw).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_tcb_info_serde() -> Result<()> {
+        let tcb_info = TcbInfo::load(include_str!("../assets/tdx_tcbinfo.json"))?;
+        let json = serde_json::to_value(&tcb_info)?;
+        assert_eq!(json["id"], "TDX");
+        assert_eq!(json["fmspc"], hex::encode(tcb_info.fmspc));
+        assert_eq!(json["tcb_levels"][0]["tcb_status"], "UpToDate");
+
+        let decoded: TcbInfo = serde_json::from_value(json)?;
+        assert_eq!(decoded.tcb_levels[1].advisory_ids, ["INTEL-SA-00837"]);
+        assert_eq!(
+            decoded.tcb_levels[0].tcb.tdxtcbcomponents,
+            tcb_info.tcb_levels[0].tcb.tdxtcbcomponents
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid_tcb_info_json() {
+        assert!(TcbInfo::load(r#"{"tcbInfo": {"version": 2}}"#).is_err());
+        assert!(TcbInfo::load("not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_tcb_status() -> Result<()> {
+        for status in [
+            TcbStatus::UpToDate,
+            TcbStatus::SWHardeningNeeded,
+            TcbStatus::ConfigurationNeeded,
+            TcbStatus::ConfigurationAndSWHardeningNeeded,
+            TcbStatus::OutOfDate,
+            TcbStatus::OutOfDateConfigurationNeeded,
+            TcbStatus::Revoked,
+        ] {
+            assert_eq!(status.as_str().parse::<TcbStatus>()?, status);
+        }
+        assert!("Unknown".parse::<TcbStatus>().is_err());
+        Ok(())
     }
 }
@@ -0,0 +1,646 @@
+//! Throwaway certificate authorities, collateral and quotes for tests, enabled by the `testing`
+//! feature.
+//!
+//! Keys are derived from fixed seeds, so certificates and signatures are deterministic. The test
+//! root CA is not the Intel SGX Root CA, verifiers must be anchored at it explicitly.
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Utc};
+use der::{
+    asn1::{BitString, ObjectIdentifier, OctetString},
+    oid::AssociatedOid,
+    pem::LineEnding,
+    Any, Encode, EncodePem, EncodeValue, Length, Sequence, Tag, Tagged, Writer,
+};
+use hex_literal::hex;
+use p256::ecdsa::{signature::Signer, DerSignature, Signature, SigningKey};
+use serde_json::value::RawValue;
+use sha2::{Digest, Sha256};
+use x509_cert::{
+    builder::{Builder, CertificateBuilder, Profile},
+    crl::{CertificateList, RevokedCert, TbsCertList},
+    ext::{AsExtension, Extension},
+    name::Name,
+    serial_number::SerialNumber,
+    spki::SubjectPublicKeyInfoOwned,
+    time::{Time, Validity},
+    Certificate, Version,
+};
+
+use crate::{
+    auth_data::{ECDSAQuoteV3AuthData, QECertData},
+    bundle::{CollateralBundle, PckCrl},
+    cert::{SgxExtensions, SgxType, Tcb, SGX_EXTENSIONS_OID},
+    collateral::Collateral,
+    quote::{EnclaveReport, Header, Quote, QuoteBody, ReportBody, TdReport10, TdReport15},
+    traits::BinRepr,
+};
+
+// 2018-01-01T00:00:00Z .. 2049-12-31T23:59:59Z
+const NOT_BEFORE: u64 = 1514764800;
+const NOT_AFTER: u64 = 2524607999;
+
+pub struct TestCa {
+    pub key: SigningKey,
+    pub cert: Certificate,
+    /// Issuers of `cert`, ordered up to the root.
+    pub issuers: Vec<Certificate>,
+}
+
+impl TestCa {
+    pub fn root(cn: &str) -> Self {
+        let key = signing_key(cn);
+        let cert = build(Profile::Root, cn, &key, &key);
+        Self {
+            key,
+            cert,
+            issuers: vec![],
+        }
+    }
+
+    pub fn issue_ca(&self, cn: &str) -> Self {
+        let profile = Profile::SubCA {
+            issuer: self.cert.tbs_certificate.subject.clone(),
+            path_len_constraint: Some(0),
+        };
+        self.issue(profile, cn)
+    }
+
+    pub fn issue_leaf(&self, cn: &str) -> Self {
+        self.issue(self.leaf_profile(), cn)
+    }
+
+    /// Issue a PCK certificate carrying the SGX extensions of a platform.
+    pub fn issue_pck(&self, cn: &str, extensions: &SgxExtensions) -> Self {
+        let key = signing_key(cn);
+        let mut builder = builder(self.leaf_profile(), cn, &key, &self.key);
+        builder
+            .add_extension(&SgxExtensionsDer::from(extensions))
+            .unwrap();
+        let cert = builder.build::<DerSignature>().unwrap();
+        self.issued(key, cert)
+    }
+
+    fn leaf_profile(&self) -> Profile {
+        Profile::Leaf {
+            issuer: self.cert.tbs_certificate.subject.clone(),
+            enable_key_agreement: false,
+            enable_key_encipherment: false,
+        }
+    }
+
+    fn issue(&self, profile: Profile, cn: &str) -> Self {
+        let key = signing_key(cn);
+        let cert = build(profile, cn, &key, &self.key);
+        self.issued(key, cert)
+    }
+
+    fn issued(&self, key: SigningKey, cert: Certificate) -> Self {
+        let mut issuers = vec![self.cert.clone()];
+        issuers.extend(self.issuers.iter().cloned());
+        Self { key, cert, issuers }
+    }
+
+    /// The TCB signing certificate issued by the test root CA.
+    pub fn tcb_signer() -> Self {
+        Self::root("Test SGX Root CA").issue_leaf("Test SGX TCB Signing")
+    }
+
+    /// A CRL issued by this CA revoking `revoked`.
+    pub fn crl(&self, revoked: &[&Certificate]) -> CertificateList {
+        let revocation_date = time(NOT_BEFORE);
+        let tbs_cert_list = TbsCertList {
+            version: Version::V2,
+            signature: self.cert.signature_algorithm.clone(),
+            issuer: self.cert.tbs_certificate.subject.clone(),
+            this_update: revocation_date,
+            next_update: Some(time(NOT_AFTER)),
+            revoked_certificates: Some(
+                revoked
+                    .iter()
+                    .map(|cert| RevokedCert {
+                        serial_number: cert.tbs_certificate.serial_number.clone(),
+                        revocation_date,
+                        crl_entry_extensions: None,
+                    })
+                    .collect(),
+            )
+            .filter(|revoked: &Vec<_>| !revoked.is_empty()),
+            crl_extensions: None,
+        };
+        let signature: DerSignature = self.key.sign(&tbs_cert_list.to_der().unwrap());
+        CertificateList {
+            tbs_cert_list,
+            signature_algorithm: self.cert.signature_algorithm.clone(),
+            signature: BitString::from_bytes(signature.as_bytes()).unwrap(),
+        }
+    }
+
+    pub fn chain_pem(&self) -> String {
+        let mut chain = vec![&self.cert];
+        chain.extend(self.issuers.iter());
+        chain_pem(&chain)
+    }
+
+    /// Re-sign a PCS JSON document (TCB Info or enclave identity) with this key.
+    pub fn sign_json(&self, raw: &str) -> String {
+        let envelope: HashMap<String, Box<RawValue>> = serde_json::from_str(raw).unwrap();
+        let (key, body) = envelope
+            .iter()
+            .find(|(key, _)| key.as_str() != "signature")
+            .unwrap();
+        let signature: p256::ecdsa::Signature = self.key.sign(body.get().as_bytes());
+        format!(
+            r#"{{"{}":{},"signature":"{}"}}"#,
+            key,
+            body.get(),
+            hex::encode(signature.to_bytes())
+        )
+    }
+}
+
+pub fn chain_pem(chain: &[&Certificate]) -> String {
+    chain
+        .iter()
+        .map(|cert| cert.to_pem(LineEnding::LF).unwrap())
+        .collect()
+}
+
+/// A platform whose PCK chain is anchored at the test root CA and whose QE matches the bundled
+/// QE or TD QE identity, so the quotes it signs verify against [`collateral`].
+pub struct TestPlatform {
+    pub pck: TestCa,
+    pub attestation_key: SigningKey,
+    pub tee_type: u32,
+    /// ISVSVN of the QE, at the UpToDate level of the QE or TD QE identity by default.
+    pub qe_svn: u16,
+}
+
+impl Default for TestPlatform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestPlatform {
+    /// FMSPC and PCE ID of the bundled TCB Info.
+    pub const FMSPC: [u8; 6] = hex!("00606a000000");
+    pub const PCE_ID: [u8; 2] = hex!("0000");
+    /// FMSPC of the bundled TDX TCB Info.
+    pub const TDX_FMSPC: [u8; 6] = hex!("00806f050000");
+    /// TEE TCB SVN of the UpToDate level of the bundled TDX TCB Info.
+    pub const TEE_TCB_SVN: [u8; 16] = [3, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    /// An SGX platform at the SWHardeningNeeded level of the bundled TCB Info.
+    pub fn new() -> Self {
+        let tcb = Tcb {
+            pce_svn: 13,
+            comp_svn_array: [12, 12, 3, 3, 255, 255, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        };
+        Self::with_tcb(&tcb)
+    }
+
+    pub fn with_tcb(tcb: &Tcb) -> Self {
+        Self::issue(Header::TEE_TYPE_SGX, Self::FMSPC, tcb)
+    }
+
+    /// A TDX platform matching the UpToDate level of the bundled TDX TCB Info.
+    pub fn tdx() -> Self {
+        let tcb = Tcb {
+            pce_svn: 11,
+            comp_svn_array: [2, 2, 2, 2, 3, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0],
+        };
+        Self::issue(Header::TEE_TYPE_TDX, Self::TDX_FMSPC, &tcb)
+    }
+
+    fn issue(tee_type: u32, fmspc: [u8; 6], tcb: &Tcb) -> Self {
+        let pck = TestCa::root("Test SGX Root CA")
+            .issue_ca("Test SGX PCK Platform CA")
+            .issue_pck("Test SGX PCK Certificate", &Self::extensions(fmspc, tcb));
+        Self {
+            pck,
+            attestation_key: signing_key("Test SGX Attestation Key"),
+            tee_type,
+            qe_svn: if tee_type == Header::TEE_TYPE_TDX {
+                4
+            } else {
+                8
+            },
+        }
+    }
+
+    /// A point in time at which the collateral for this platform is valid.
+    pub fn verification_time(&self) -> DateTime<Utc> {
+        if self.tee_type == Header::TEE_TYPE_TDX {
+            tdx_verification_time()
+        } else {
+            verification_time()
+        }
+    }
+
+    /// The SGX extensions of a single-package platform with the given TCB.
+    pub fn extensions(fmspc: [u8; 6], tcb: &Tcb) -> SgxExtensions {
+        SgxExtensions {
+            ppid: Sha256::digest(fmspc)[..16].try_into().unwrap(),
+            tcb: tcb.clone(),
+            cpu_svn: tcb.comp_svn_array,
+            pce_id: Self::PCE_ID,
+            fmspc,
+            sgx_type: SgxType::Standard,
+            platform_instance_id: None,
+            configuration: None,
+        }
+    }
+
+    /// A quote body for an enclave or TD on this platform. TDX quotes carry a TD Report 1.5
+    /// from v5 on.
+    pub fn quote_body(&self, version: u16) -> QuoteBody {
+        let report = if self.tee_type == Header::TEE_TYPE_TDX {
+            let mut td_report = TdReport10::from_bytes(&[0; 584]).unwrap();
+            td_report.tee_tcb_svn = Self::TEE_TCB_SVN;
+            td_report.mr_td = [0x33; 48];
+            if version < 5 {
+                ReportBody::Td10(td_report)
+            } else {
+                ReportBody::Td15(TdReport15 {
+                    td_report,
+                    tee_tcb_svn2: Self::TEE_TCB_SVN,
+                    mr_service_td: [0; 48],
+                })
+            }
+        } else {
+            let mut enclave_report = EnclaveReport::from_bytes(&[0; 384]).unwrap();
+            enclave_report.mr_enclave = [0x11; 32];
+            enclave_report.mr_signer = [0x22; 32];
+            ReportBody::Sgx(enclave_report)
+        };
+        QuoteBody {
+            header: Header {
+                version,
+                attestation_key_type: 2,
+                tee_type: self.tee_type,
+                qe_svn: 8,
+                pce_svn: 13,
+                qe_vendor_id: hex!("939a7233f79c4ca9940a0db3957f0607"),
+                user_data: [0; 20],
+            },
+            report,
+        }
+    }
+
+    /// Sign `body` the way the QE (or TD QE) does, certifying the attestation key with the PCK.
+    pub fn sign(&self, body: &QuoteBody) -> ECDSAQuoteV3AuthData {
+        self.sign_with(body, |_| {})
+    }
+
+    /// Same as [`TestPlatform::sign`], with the QE report edited before the PCK signs it.
+    pub fn sign_with(
+        &self,
+        body: &QuoteBody,
+        edit_qe_report: impl FnOnce(&mut EnclaveReport),
+    ) -> ECDSAQuoteV3AuthData {
+        let ecdsa_attestation_key: [u8; 64] = self
+            .attestation_key
+            .verifying_key()
+            .to_encoded_point(false)
+            .as_bytes()[1..]
+            .try_into()
+            .unwrap();
+        let qe_auth_data = (0..32).collect::<Vec<u8>>();
+
+        let mut qe_report = EnclaveReport::from_bytes(&[0; 384]).unwrap();
+        qe_report.attributes = hex!("11000000000000000000000000000000");
+        if self.tee_type == Header::TEE_TYPE_TDX {
+            qe_report.mr_signer =
+                hex!("dc9e2a7c6f948f17474e34a7fc43ed030f7c1563f1babddf6340c82e0e54a8c5");
+            qe_report.isv_prod_id = 2;
+        } else {
+            qe_report.mr_signer =
+                hex!("8c4f5775d796503e96137f77c68a829a0056ac8ded70140b081b094490c57bff");
+            qe_report.isv_prod_id = 1;
+        }
+        qe_report.isv_svn = self.qe_svn;
+        let hash = Sha256::new()
+            .chain_update(ecdsa_attestation_key)
+            .chain_update(&qe_auth_data)
+            .finalize();
+        qe_report.report_data[..32].copy_from_slice(&hash);
+        edit_qe_report(&mut qe_report);
+        let raw_qe_report: [u8; 384] = qe_report.to_bytes().unwrap().try_into().unwrap();
+
+        let qe_report_signature: Signature = self.pck.key.sign(&raw_qe_report);
+        let signature: Signature = self.attestation_key.sign(&body.to_bytes().unwrap());
+        ECDSAQuoteV3AuthData {
+            ecdsa256_bit_signature: signature.to_bytes().into(),
+            ecdsa_attestation_key,
+            raw_qe_report,
+            qe_report_signature: qe_report_signature.to_bytes().into(),
+            qe_auth_data,
+            qe_cert: QECertData {
+                cert_type: 5,
+                cert_data: self.pck.chain_pem().into_bytes(),
+            },
+        }
+    }
+}
+
+/// Assembles quotes of a [`TestPlatform`] with arbitrary header, report and QE report fields,
+/// signed by the platform. Fields that no longer match the collateral or policy exercise the
+/// failure paths of verification.
+pub struct QuoteBuilder {
+    platform: TestPlatform,
+    body: QuoteBody,
+    edit_qe_report: Box<dyn FnOnce(&mut EnclaveReport)>,
+}
+
+impl QuoteBuilder {
+    /// A v3 quote of `platform`.
+    pub fn new(platform: TestPlatform) -> Self {
+        Self {
+            body: platform.quote_body(3),
+            platform,
+            edit_qe_report: Box::new(|_| {}),
+        }
+    }
+
+    /// A v3 quote of the SGX [`TestPlatform`], which verifies against [`collateral`].
+    pub fn sgx() -> Self {
+        Self::new(TestPlatform::new())
+    }
+
+    pub fn header(mut self, edit: impl FnOnce(&mut Header)) -> Self {
+        edit(&mut self.body.header);
+        self
+    }
+
+    /// Edit the enclave report. Panics if the platform is not an SGX platform.
+    pub fn enclave_report(mut self, edit: impl FnOnce(&mut EnclaveReport)) -> Self {
+        match &mut self.body.report {
+            ReportBody::Sgx(report) => edit(report),
+            _ => panic!("Not an SGX quote"),
+        }
+        self
+    }
+
+    /// Edit the QE report after it has committed to the attestation key, before it is signed.
+    pub fn qe_report(mut self, edit: impl FnOnce(&mut EnclaveReport) + 'static) -> Self {
+        self.edit_qe_report = Box::new(edit);
+        self
+    }
+
+    pub fn build(self) -> Quote<ECDSAQuoteV3AuthData> {
+        let signature = self.platform.sign_with(&self.body, self.edit_qe_report);
+        Quote {
+            body: self.body,
+            signature,
+        }
+    }
+}
+
+#[derive(Sequence)]
+struct SgxExtension {
+    key: ObjectIdentifier,
+    value: Any,
+}
+
+impl SgxExtension {
+    fn new(arc: &str, value: impl Tagged + EncodeValue) -> Self {
+        Self {
+            key: ObjectIdentifier::new_unwrap(&format!("{}.{}", SGX_EXTENSIONS_OID, arc)),
+            value: Any::encode_from(&value).unwrap(),
+        }
+    }
+}
+
+/// The DER encoding of [`SgxExtensions`] as a certificate extension.
+struct SgxExtensionsDer(Vec<SgxExtension>);
+
+impl From<&SgxExtensions> for SgxExtensionsDer {
+    fn from(exts: &SgxExtensions) -> Self {
+        let octets = |bytes: &[u8]| OctetString::new(bytes).unwrap();
+        let mut tcb_exts = exts
+            .tcb
+            .comp_svn_array
+            .iter()
+            .enumerate()
+            .map(|(i, svn)| SgxExtension::new(&format!("2.{}", i + 1), *svn))
+            .collect::<Vec<_>>();
+        tcb_exts.push(SgxExtension::new("2.17", exts.tcb.pce_svn));
+        tcb_exts.push(SgxExtension::new("2.18", octets(&exts.cpu_svn)));
+
+        let sgx_type = match exts.sgx_type {
+            SgxType::Standard => 0,
+            SgxType::Scalable => 1,
+            SgxType::ScalableWithIntegrity => 2,
+        };
+        let mut der = vec![
+            SgxExtension::new("1", octets(&exts.ppid)),
+            SgxExtension::new("2", tcb_exts),
+            SgxExtension::new("3", octets(&exts.pce_id)),
+            SgxExtension::new("4", octets(&exts.fmspc)),
+            SgxExtension {
+                key: ObjectIdentifier::new_unwrap(&format!("{}.5", SGX_EXTENSIONS_OID)),
+                value: Any::new(Tag::Enumerated, [sgx_type]).unwrap(),
+            },
+        ];
+        if let Some(platform_instance_id) = &exts.platform_instance_id {
+            der.push(SgxExtension::new("6", octets(platform_instance_id)));
+        }
+        if let Some(configuration) = &exts.configuration {
+            let flags = [
+                ("7.1", configuration.dynamic_platform),
+                ("7.2", configuration.cached_keys),
+                ("7.3", configuration.smt_enabled),
+            ];
+            let flags = flags
+                .iter()
+                .filter_map(|(arc, flag)| flag.map(|flag| SgxExtension::new(arc, flag)))
+                .collect::<Vec<_>>();
+            der.push(SgxExtension::new("7", flags));
+        }
+        Self(der)
+    }
+}
+
+impl AssociatedOid for SgxExtensionsDer {
+    const OID: ObjectIdentifier = SGX_EXTENSIONS_OID;
+}
+
+impl Encode for SgxExtensionsDer {
+    fn encoded_len(&self) -> der::Result<Length> {
+        self.0.encoded_len()
+    }
+
+    fn encode(&self, writer: &mut impl Writer) -> der::Result<()> {
+        self.0.encode(writer)
+    }
+}
+
+impl AsExtension for SgxExtensionsDer {
+    fn critical(&self, _: &Name, _: &[Extension]) -> bool {
+        false
+    }
+}
+
+/// A point in time at which the bundled SGX TCB Info, QE Identity and the quote fixtures are
+/// valid.
+pub fn verification_time() -> DateTime<Utc> {
+    "2023-10-01T00:00:00Z".parse().unwrap()
+}
+
+/// A point in time at which the bundled TDX TCB Info and TD QE Identity are valid.
+pub fn tdx_verification_time() -> DateTime<Utc> {
+    "2024-04-01T00:00:00Z".parse().unwrap()
+}
+
+/// Collateral built from the bundled assets, re-signed by [`TestCa::tcb_signer`] and
+/// anchored at the test root CA, with empty CRLs from the root and PCK Platform CA.
+///
+/// The TDX TCB Info and TD QE Identity assets are synthetic, shaped after the PCS documents.
+pub fn collateral() -> Collateral {
+    bundle()
+        .collateral_with_root_ca(TestCa::tcb_signer().issuers[0].clone())
+        .unwrap()
+}
+
+/// The documents and CRLs of [`collateral`] as a collateral bundle.
+pub fn bundle() -> CollateralBundle {
+    let signer = TestCa::tcb_signer();
+    let root = TestCa::root("Test SGX Root CA");
+    let ca = root.issue_ca("Test SGX PCK Platform CA");
+    CollateralBundle {
+        version: CollateralBundle::VERSION,
+        root_ca: chain_pem(&[&root.cert]),
+        tcb_signing_chain: signer.chain_pem(),
+        tcb_infos: vec![
+            signer.sign_json(include_str!("../assets/tcbinfo.json")),
+            signer.sign_json(include_str!("../assets/tdx_tcbinfo.json")),
+        ],
+        qe_identity: signer.sign_json(include_str!("../assets/identity.json")),
+        td_qe_identity: Some(signer.sign_json(include_str!("../assets/td_identity.json"))),
+        root_ca_crl: Some(hex::encode(root.crl(&[]).to_der().unwrap())),
+        pck_crls: vec![PckCrl {
+            crl: pem::encode(&pem::Pem::new("X509 CRL", ca.crl(&[]).to_der().unwrap())),
+            issuer_chain: chain_pem(&[&ca.cert, &root.cert]),
+        }],
+    }
+}
+
+/// Public key of the Intel SGX TCB Signing certificate that signed `assets/pcs_*.json`,
+/// recovered from those signatures as the certificate isn't vendored.
+#[cfg(test)]
+pub(crate) const INTEL_TCB_SIGNING_KEY: [u8; 65] = hex!(
+    "0443451bcc73c9d5917caf766e61af3fe98087dd4f13257b261e851897799dd1"
+    "3d6811fb47713803bb9bae587fccddc2e31be9a28b86962acc6daf96da58eeca96"
+);
+
+/// The genuine PCS documents anchored at the Intel SGX Root CA, matching the genuine quotes
+/// used as fixtures.
+#[cfg(test)]
+pub(crate) fn intel_collateral() -> Collateral {
+    let mut collateral = Collateral::with_tcb_signing_key(
+        &crate::signature::VerifyingKey::from_sec1_bytes(INTEL_TCB_SIGNING_KEY).unwrap(),
+        &[include_str!("../assets/pcs_tcbinfo.json")],
+        include_str!("../assets/pcs_identity.json"),
+    )
+    .unwrap();
+    // Intel's CRLs are not vendored.
+    collateral.allow_missing_crls();
+    collateral
+}
+
+fn signing_key(seed: &str) -> SigningKey {
+    SigningKey::from_slice(&Sha256::digest(seed.as_bytes())).unwrap()
+}
+
+fn time(unix: u64) -> Time {
+    Time::try_from(SystemTime::UNIX_EPOCH + Duration::from_secs(unix)).unwrap()
+}
+
+fn build(profile: Profile, cn: &str, key: &SigningKey, issuer_key: &SigningKey) -> Certificate {
+    builder(profile, cn, key, issuer_key)
+        .build::<DerSignature>()
+        .unwrap()
+}
+
+fn builder<'s>(
+    profile: Profile,
+    cn: &str,
+    key: &SigningKey,
+    issuer_key: &'s SigningKey,
+) -> CertificateBuilder<'s, SigningKey> {
+    let validity = Validity {
+        not_before: time(NOT_BEFORE),
+        not_after: time(NOT_AFTER),
+    };
+    let subject = Name::from_str(&format!("CN={},O=Test Corporation,C=US", cn)).unwrap();
+    let spki = SubjectPublicKeyInfoOwned::from_key(*key.verifying_key()).unwrap();
+    let mut serial = Sha256::digest(cn.as_bytes())[..16].to_vec();
+    serial[0] &= 0x7f;
+    let serial = SerialNumber::new(&serial).unwrap();
+
+    CertificateBuilder::new(profile, serial, validity, subject, spki, issuer_key).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::{
+        error::{DcapError, VerificationStep},
+        policy::EnclavePolicy,
+        tcb_info::TcbStatus,
+    };
+
+    #[test]
+    fn test_quote_builder() -> Result<()> {
+        let collateral = collateral();
+        let policy = EnclavePolicy::default().trust_signer([0x22; 32]);
+        let now = verification_time();
+        let verify = |quote: Quote<ECDSAQuoteV3AuthData>, collateral: &Collateral| {
+            quote.verify_steps_at(collateral, &policy, now)
+        };
+
+        let report = verify(QuoteBuilder::sgx().build(), &collateral)?;
+        assert_eq!(report.tcb_status(), TcbStatus::SWHardeningNeeded);
+
+        let quote = QuoteBuilder::sgx()
+            .enclave_report(|report| report.mr_signer = [0x44; 32])
+            .build();
+        let err = verify(quote, &collateral).unwrap_err();
+        assert_eq!(err.step, VerificationStep::Policy);
+
+        let quote = QuoteBuilder::sgx()
+            .qe_report(|report| report.report_data[0] ^= 1)
+            .build();
+        let err = verify(quote, &collateral).unwrap_err();
+        assert_eq!(err.step, VerificationStep::QeReportSignature);
+        assert!(matches!(err.error, DcapError::Signature(_)));
+
+        let quote = QuoteBuilder::sgx()
+            .header(|header| header.qe_svn = 9)
+            .build();
+        assert!(verify(quote, &collateral).is_ok());
+
+        // The platform's TCB level revoked.
+        let signer = TestCa::tcb_signer();
+        let tcb_info =
+            include_str!("../assets/tcbinfo.json").replace("\"SWHardeningNeeded\"", "\"Revoked\"");
+        let revoked = Collateral::with_root_ca(
+            signer.issuers[0].clone(),
+            &[signer.sign_json(&tcb_info)],
+            signer.sign_json(include_str!("../assets/identity.json")),
+            signer.chain_pem(),
+        )?;
+        let err = verify(QuoteBuilder::sgx().build(), &revoked).unwrap_err();
+        assert_eq!(err.step, VerificationStep::Tcb);
+        assert_eq!(err.error, DcapError::TcbStatus(TcbStatus::Revoked));
+        Ok(())
+    }
+}
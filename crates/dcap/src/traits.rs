@@ -1,4 +1,5 @@
-use anyhow::Result;
+use super::error::{Result, StepResult};
+use alloc::vec::Vec;
 
 pub trait BinRepr: Sized {
     fn from_bytes(bytes: &[u8]) -> Result<Self>;
@@ -7,12 +8,20 @@ pub trait BinRepr: Sized {
 }
 
 pub trait Verifiable {
-    type Payload;
+    type Payload<'a>;
     type Output;
+    type Report;
 
-    fn verify(&self, payload: &Self::Payload) -> Result<()>;
+    fn verify(&self, payload: &Self::Payload<'_>) -> Result<Self::Report>;
 
-    fn paramlized(&self, payload: &Self::Payload) -> Result<Self::Output> {
+    fn paramlized(&self, _payload: &Self::Payload<'_>) -> Result<Self::Output> {
         unimplemented!()
     }
 }
+
+/// Quote signature data whose verification reports the [`VerificationStep`] that failed.
+///
+/// [`VerificationStep`]: crate::VerificationStep
+pub trait StepVerifiable: Verifiable {
+    fn verify_steps(&self, payload: &Self::Payload<'_>) -> StepResult<Self::Report>;
+}